@@ -0,0 +1,40 @@
+use std::io;
+use std::net::IpAddr;
+use std::vec;
+
+use futures::{Future, Poll};
+use futures::stream::Stream;
+use tokio_core::io::{IoFuture, IoStream};
+
+// Stream which yields the addresses of a resolution once it completes.
+struct Addresses {
+    resolve: Option<IoFuture<Vec<IpAddr>>>,
+    addrs: vec::IntoIter<IpAddr>,
+}
+
+impl Stream for Addresses {
+    type Item = IpAddr;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if let Some(mut resolve) = self.resolve.take() {
+            match resolve.poll() {
+                Poll::Ok(addrs) => self.addrs = addrs.into_iter(),
+                Poll::Err(e) => return Poll::Err(e),
+                Poll::NotReady => {
+                    self.resolve = Some(resolve);
+                    return Poll::NotReady;
+                }
+            }
+        }
+
+        Poll::Ok(self.addrs.next())
+    }
+}
+
+pub fn addresses(resolve: IoFuture<Vec<IpAddr>>) -> IoStream<IpAddr> {
+    Addresses {
+        resolve: Some(resolve),
+        addrs: Vec::new().into_iter(),
+    }.boxed()
+}
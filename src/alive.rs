@@ -0,0 +1,38 @@
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+use libc;
+use tokio_core::TcpStream;
+
+/// Check whether the peer of a connection has closed it.
+///
+/// This peeks at the socket without blocking and without consuming any
+/// data. It returns `false` if the peer closed the connection or if the
+/// connection is in an error state, and `true` if data is available or if
+/// reading would block. It is useful to avoid reusing a dead pooled connection.
+///
+/// This function is only available on Unix platforms.
+pub fn is_connection_alive(stream: &TcpStream) -> bool {
+    let mut buf = [0u8; 1];
+
+    loop {
+        let ret = unsafe {
+            libc::recv(stream.as_raw_fd(),
+                       buf.as_mut_ptr() as *mut libc::c_void,
+                       buf.len(),
+                       libc::MSG_PEEK | libc::MSG_DONTWAIT)
+        };
+
+        if ret > 0 {
+            return true;
+        } else if ret == 0 {
+            return false;
+        }
+
+        match io::Error::last_os_error().kind() {
+            io::ErrorKind::Interrupted => continue,
+            io::ErrorKind::WouldBlock => return true,
+            _ => return false,
+        }
+    }
+}
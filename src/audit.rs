@@ -0,0 +1,128 @@
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Sender, SyncSender, TrySendError};
+use std::time::SystemTime;
+
+use futures::Future;
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A record of a resolution made by an `AuditResolver`.
+#[derive(Clone, Debug)]
+pub struct AuditRecord {
+    /// When the resolution started.
+    pub time: SystemTime,
+    /// The host name that was resolved.
+    pub host: String,
+    /// The addresses returned, or the message of the error.
+    pub result: Result<Vec<IpAddr>, String>,
+}
+
+/// A resolver that records every resolution.
+///
+/// Each resolution made by the inner resolver produces an `AuditRecord`
+/// that is sent to a channel once the resolution completes. Sending never
+/// blocks the resolution: with a bounded channel, records are dropped
+/// while the channel is full, with an unbounded channel, they are all buffered.
+#[derive(Clone)]
+pub struct AuditResolver<R> {
+    resolver: R,
+    sink: Arc<Mutex<Sink>>,
+}
+
+enum Sink {
+    Bounded(SyncSender<AuditRecord>),
+    Unbounded(Sender<AuditRecord>),
+}
+
+impl<R> AuditResolver<R> where R: Resolver {
+    /// Create a new AuditResolver sending records to a bounded channel.
+    ///
+    /// Records that don't fit in the channel are dropped.
+    pub fn new(resolver: R, sink: SyncSender<AuditRecord>) -> Self {
+        AuditResolver {
+            resolver: resolver,
+            sink: Arc::new(Mutex::new(Sink::Bounded(sink))),
+        }
+    }
+
+    /// Create a new AuditResolver sending records to an unbounded channel.
+    ///
+    /// Every record is buffered until it is received.
+    pub fn unbounded(resolver: R, sink: Sender<AuditRecord>) -> Self {
+        AuditResolver {
+            resolver: resolver,
+            sink: Arc::new(Mutex::new(Sink::Unbounded(sink))),
+        }
+    }
+}
+
+impl<R> Resolver for AuditResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let time = SystemTime::now();
+        let sink = self.sink.clone();
+        let host = host.to_owned();
+
+        self.resolver.resolve(&host).then(move |res| {
+            let record = AuditRecord {
+                time: time,
+                host: host,
+                result: match res {
+                    Ok(ref addrs) => Ok(addrs.clone()),
+                    Err(ref e) => Err(e.to_string()),
+                },
+            };
+
+            match *sink.lock().unwrap() {
+                Sink::Bounded(ref sender) => {
+                    if let Err(TrySendError::Full(record)) = sender.try_send(record) {
+                        warn!("dropping the audit record of {}, the channel is full", record.host);
+                    }
+                }
+                Sink::Unbounded(ref sender) => {
+                    let _ = sender.send(record);
+                }
+            }
+
+            res
+        }).boxed()
+    }
+}
+
+#[test]
+fn test_audit() {
+    use std::io;
+    use std::net::Ipv4Addr;
+    use std::sync::mpsc::sync_channel;
+    use futures::{failed, finished};
+
+    struct Fixed;
+
+    impl Resolver for Fixed {
+        fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+            if host == "example.com" {
+                finished(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]).boxed()
+            } else {
+                failed(io::Error::new(io::ErrorKind::Other, "not found")).boxed()
+            }
+        }
+    }
+
+    let (tx, rx) = sync_channel(2);
+    let resolver = AuditResolver::new(Fixed, tx);
+
+    assert!(resolver.resolve("example.com").wait().is_ok());
+    assert!(resolver.resolve("example.org").wait().is_err());
+    assert!(resolver.resolve("example.net").wait().is_err());
+
+    let record = rx.recv().unwrap();
+    assert_eq!(record.host, "example.com");
+    assert_eq!(record.result, Ok(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]));
+
+    let record = rx.recv().unwrap();
+    assert_eq!(record.host, "example.org");
+    assert_eq!(record.result, Err("not found".to_owned()));
+
+    assert!(rx.try_recv().is_err());
+}
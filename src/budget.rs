@@ -0,0 +1,116 @@
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::{failed, Future};
+use tokio_core::LoopHandle;
+use tokio_core::io::IoFuture;
+
+use super::common::{timed_out, with_deadline};
+use super::Resolver;
+
+/// A resolver that shares a time budget between its resolutions.
+///
+/// Each resolution may take at most what is left of the budget, and
+/// the time it took is then taken off the budget. A resolution that
+/// exceeds the remaining budget fails with an error of kind `TimedOut`,
+/// and once the budget is exhausted, resolutions fail immediately.
+///
+/// The clones of the resolver share the same budget. The time of
+/// concurrent resolutions is taken off the budget for each of them.
+#[derive(Clone)]
+pub struct BudgetResolver<R> {
+    handle: LoopHandle,
+    resolver: R,
+    remaining: Arc<Mutex<Duration>>,
+}
+
+impl<R> BudgetResolver<R> where R: Resolver {
+    /// Create a new BudgetResolver with the given budget.
+    pub fn new(handle: LoopHandle, resolver: R, budget: Duration) -> Self {
+        BudgetResolver {
+            handle: handle,
+            resolver: resolver,
+            remaining: Arc::new(Mutex::new(budget)),
+        }
+    }
+
+    /// Get what is left of the budget.
+    pub fn remaining(&self) -> Duration {
+        *self.remaining.lock().unwrap()
+    }
+}
+
+impl<R> Resolver for BudgetResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let remaining = self.remaining();
+        if remaining == Duration::new(0, 0) {
+            debug!("not resolving {}, the budget is exhausted", host);
+            return failed(timed_out()).boxed();
+        }
+
+        let start = Instant::now();
+        let budget = self.remaining.clone();
+        let fut = with_deadline(self.handle.clone(), start + remaining, || self.resolver.resolve(host));
+
+        fut.then(move |res| {
+            let elapsed = start.elapsed();
+            let mut remaining = budget.lock().unwrap();
+            *remaining = if elapsed >= *remaining {
+                Duration::new(0, 0)
+            } else {
+                *remaining - elapsed
+            };
+            res
+        }).boxed()
+    }
+}
+
+#[test]
+fn test_budget_exhausted() {
+    use std::io;
+    use futures::finished;
+    use tokio_core::Loop;
+
+    struct Unused;
+
+    impl Resolver for Unused {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished(vec![]).boxed()
+        }
+    }
+
+    let lp = Loop::new().unwrap();
+    let resolver = BudgetResolver::new(lp.handle(), Unused, Duration::new(0, 0));
+    let e = resolver.resolve("example.com").wait().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn test_budget_used_up() {
+    use std::io;
+    use std::net::Ipv4Addr;
+    use std::thread;
+    use futures::finished;
+    use tokio_core::Loop;
+
+    struct Slow;
+
+    impl Resolver for Slow {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            thread::sleep(Duration::from_millis(40));
+            finished(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]).boxed()
+        }
+    }
+
+    let mut lp = Loop::new().unwrap();
+    let resolver = BudgetResolver::new(lp.handle(), Slow, Duration::from_millis(30));
+
+    // The resolution completes, but takes more than the whole budget.
+    let addrs = lp.run(resolver.resolve("example.com")).unwrap();
+    assert_eq!(addrs, vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+    assert_eq!(resolver.remaining(), Duration::new(0, 0));
+
+    let e = lp.run(resolver.resolve("example.com")).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+}
@@ -0,0 +1,90 @@
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+
+// The capacity of the buffers of a BufStream created with `new`.
+pub const DEFAULT_CAPACITY: usize = 8192;
+
+// Lets the read buffer wrap the write buffer.
+struct Writer<S: Write>(BufWriter<S>);
+
+impl<S> Read for Writer<S> where S: Read + Write {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.get_mut().read(buf)
+    }
+}
+
+/// A stream with buffered reads and writes.
+///
+/// The reads are buffered like with a `BufReader`, and the writes like
+/// with a `BufWriter`. Data written stays in the buffer until it is full
+/// or until the stream is flushed, like with `tokio_core::io::flush`.
+pub struct BufStream<S: Write> {
+    inner: BufReader<Writer<S>>,
+}
+
+impl<S> BufStream<S> where S: Read + Write {
+    /// Wrap a stream with buffers of 8 KiB.
+    pub fn new(stream: S) -> Self {
+        BufStream::with_capacities(DEFAULT_CAPACITY, DEFAULT_CAPACITY, stream)
+    }
+
+    /// Wrap a stream with buffers of the given capacities.
+    pub fn with_capacities(read: usize, write: usize, stream: S) -> Self {
+        BufStream {
+            inner: BufReader::with_capacity(read, Writer(BufWriter::with_capacity(write, stream))),
+        }
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref().0.get_ref()
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    ///
+    /// Reading from or writing to it directly bypasses the buffers.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.inner.get_mut().0.get_mut()
+    }
+}
+
+impl<S> Read for BufStream<S> where S: Read + Write {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<S> BufRead for BufStream<S> where S: Read + Write {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+impl<S> Write for BufStream<S> where S: Read + Write {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.get_mut().0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.get_mut().0.flush()
+    }
+}
+
+#[test]
+fn test_buffered() {
+    use std::io::Cursor;
+
+    let mut stream = BufStream::with_capacities(4, 4, Cursor::new(b"line\nrest".to_vec()));
+
+    let mut line = String::new();
+    stream.read_line(&mut line).unwrap();
+    assert_eq!(line, "line\n");
+
+    stream.write_all(b"ab").unwrap();
+    assert_eq!(stream.get_ref().get_ref(), b"line\nrest");
+    stream.flush().unwrap();
+    assert!(stream.get_ref().get_ref().ends_with(b"ab"));
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use futures::{empty, failed, oneshot, Complete, Future, Oneshot};
+use tokio_core::io::IoFuture;
+
+struct Inner {
+    canceled: bool,
+    next_id: usize,
+    waiters: HashMap<usize, Complete<()>>,
+}
+
+// Removes the waiter of a cancelable future once it completes or is dropped.
+struct Waiter {
+    inner: Arc<Mutex<Inner>>,
+    id: usize,
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        self.inner.lock().unwrap().waiters.remove(&self.id);
+    }
+}
+
+/// The sending half of a cancellation signal.
+///
+/// Calling `cancel` aborts every future made cancelable with one of the
+/// associated `CancelToken`s.
+pub struct Canceler {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Canceler {
+    /// Abort every future tied to the tokens of this canceler.
+    ///
+    /// The futures made cancelable after this call are aborted immediately.
+    pub fn cancel(&self) {
+        let waiters = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.canceled = true;
+            mem::replace(&mut inner.waiters, HashMap::new())
+        };
+
+        debug!("canceling {} futures", waiters.len());
+
+        for (_, waiter) in waiters {
+            waiter.complete(());
+        }
+    }
+}
+
+/// The receiving half of a cancellation signal.
+///
+/// A token can be cloned and shared by many futures, so that they can be
+/// cancelled as a group.
+#[derive(Clone)]
+pub struct CancelToken {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl CancelToken {
+    fn wait(&self) -> (Oneshot<()>, Option<Waiter>) {
+        let (tx, rx) = oneshot();
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.canceled {
+            tx.complete(());
+            return (rx, None);
+        }
+
+        let id = inner.next_id;
+        inner.next_id = inner.next_id.wrapping_add(1);
+        inner.waiters.insert(id, tx);
+
+        (rx, Some(Waiter { inner: self.inner.clone(), id: id }))
+    }
+}
+
+/// Create a new cancellation signal.
+pub fn cancel_token() -> (Canceler, CancelToken) {
+    let inner = Arc::new(Mutex::new(Inner {
+        canceled: false,
+        next_id: 0,
+        waiters: HashMap::new(),
+    }));
+
+    (Canceler { inner: inner.clone() }, CancelToken { inner: inner })
+}
+
+/// Make a future abort when the given token is cancelled.
+///
+/// This works with every future returned by this crate, such as the one of
+/// `tcp_connect_par`. If the token is cancelled before the future completes,
+/// the future is dropped and an error of kind `Interrupted` is returned.
+pub fn cancelable<F>(fut: F, token: &CancelToken) -> IoFuture<F::Item>
+    where F: Future<Error = io::Error> + Send + 'static,
+          F::Item: Send + 'static
+{
+    let (wait, waiter) = token.wait();
+    let canceled = wait.then(|res| {
+        match res {
+            Ok(()) => failed(io::Error::new(io::ErrorKind::Interrupted, "operation canceled")).boxed(),
+            // the canceler is gone, so the token can never be cancelled
+            Err(_) => empty().boxed(),
+        }
+    });
+
+    fut.boxed().select(canceled).then(move |res| {
+        // The token no longer has to signal this future.
+        drop(waiter);
+        match res {
+            Ok((item, _)) => Ok(item),
+            Err((e, _)) => Err(e),
+        }
+    }).boxed()
+}
+
+/// A handle to abort a single future.
+///
+/// It is created along with an abortable future by `abortable`.
+pub struct AbortHandle {
+    canceler: Canceler,
+}
+
+impl AbortHandle {
+    /// Abort the future associated with this handle.
+    ///
+    /// The future fails with an error of kind `Interrupted`. Work that was
+    /// handed to a thread pool, like the `getaddrinfo` call of the
+    /// `CpuPoolResolver`, can't be stopped: it runs to completion and its
+    /// result is discarded.
+    pub fn abort(&self) {
+        self.canceler.cancel();
+    }
+}
+
+/// Make a future abortable.
+///
+/// This is a shorthand for `cancelable` with a token used by a single future.
+/// It is typically used with the future of `Resolver::resolve` or of a
+/// connection helper, to let a user abort an operation in progress.
+pub fn abortable<F>(fut: F) -> (IoFuture<F::Item>, AbortHandle)
+    where F: Future<Error = io::Error> + Send + 'static,
+          F::Item: Send + 'static
+{
+    let (canceler, token) = cancel_token();
+    (cancelable(fut, &token), AbortHandle { canceler: canceler })
+}
+
+#[test]
+fn test_cancel() {
+    let (canceler, token) = cancel_token();
+    let fut = cancelable(empty::<(), io::Error>(), &token);
+
+    canceler.cancel();
+    assert_eq!(fut.wait().unwrap_err().kind(), io::ErrorKind::Interrupted);
+
+    let fut = cancelable(empty::<(), io::Error>(), &token);
+    assert_eq!(fut.wait().unwrap_err().kind(), io::ErrorKind::Interrupted);
+}
+
+#[test]
+fn test_not_canceled() {
+    use futures::finished;
+
+    let (_canceler, token) = cancel_token();
+    let fut = cancelable(finished::<u32, io::Error>(3), &token);
+    assert_eq!(fut.wait().unwrap(), 3);
+}
+
+#[test]
+fn test_waiters_removed() {
+    use futures::finished;
+
+    let (_canceler, token) = cancel_token();
+    for i in 0..10 {
+        assert_eq!(cancelable(finished::<u32, io::Error>(i), &token).wait().unwrap(), i);
+    }
+
+    // A future dropped before completing also gives up its waiter.
+    drop(cancelable(empty::<(), io::Error>(), &token));
+
+    assert!(token.inner.lock().unwrap().waiters.is_empty());
+}
+
+#[test]
+fn test_abort() {
+    let (fut, handle) = abortable(empty::<(), io::Error>());
+
+    handle.abort();
+    assert_eq!(fut.wait().unwrap_err().kind(), io::ErrorKind::Interrupted);
+}
@@ -0,0 +1,63 @@
+use std::io;
+use std::net::SocketAddr;
+
+use futures::{Future, Poll};
+use futures::stream::Stream;
+use tokio_core::{LoopHandle, TcpStream};
+use tokio_core::io::{IoFuture, IoStream};
+
+use super::common::{log_attempt, resolve_endpoint_stream};
+use super::{Resolver, ToEndpoint};
+
+// Stream which connects to the resolved addresses one at a time, when it is polled.
+// The addresses are used as soon as the resolver yields them.
+struct ConnectCandidates {
+    handle: LoopHandle,
+    addrs: IoStream<SocketAddr>,
+    attempt: Option<(SocketAddr, IoFuture<TcpStream>)>,
+}
+
+impl Stream for ConnectCandidates {
+    type Item = (SocketAddr, io::Result<TcpStream>);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if self.attempt.is_none() {
+            match self.addrs.poll() {
+                Poll::Ok(Some(addr)) => {
+                    let fut = log_attempt(addr, self.handle.clone().tcp_connect(&addr));
+                    self.attempt = Some((addr, fut));
+                }
+                Poll::Ok(None) => return Poll::Ok(None),
+                Poll::Err(e) => return Poll::Err(e),
+                Poll::NotReady => return Poll::NotReady,
+            }
+        }
+
+        let res = match self.attempt {
+            Some((_, ref mut fut)) => {
+                match fut.poll() {
+                    Poll::Ok(stream) => Ok(stream),
+                    Poll::Err(e) => Err(e),
+                    Poll::NotReady => return Poll::NotReady,
+                }
+            }
+            None => unreachable!(),
+        };
+
+        let (addr, _) = self.attempt.take().unwrap();
+        Poll::Ok(Some((addr, res)))
+    }
+}
+
+pub fn tcp_connect_candidates<'a, R, T>(handle: LoopHandle, resolver: R, ep: T)
+                                        -> IoStream<(SocketAddr, io::Result<TcpStream>)>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    ConnectCandidates {
+        handle: handle,
+        addrs: resolve_endpoint_stream(resolver, ep),
+        attempt: None,
+    }.boxed()
+}
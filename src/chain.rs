@@ -0,0 +1,44 @@
+use std::net::IpAddr;
+
+use futures::Future;
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A resolver that falls back to a second resolver.
+///
+/// The host name is first given to the `first` resolver. If it fails,
+/// the host name is given to the `second` resolver.
+#[derive(Clone)]
+pub struct ChainResolver<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> ChainResolver<A, B>
+    where A: Resolver,
+          B: Resolver + Clone + Send + 'static
+{
+    /// Create a new ChainResolver from the two given resolvers.
+    pub fn new(first: A, second: B) -> Self {
+        ChainResolver {
+            first: first,
+            second: second,
+        }
+    }
+}
+
+impl<A, B> Resolver for ChainResolver<A, B>
+    where A: Resolver,
+          B: Resolver + Clone + Send + 'static
+{
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let second = self.second.clone();
+        let host = host.to_owned();
+
+        self.first.resolve(&host).or_else(move |e| {
+            debug!("falling back to the second resolver for {}: {}", host, e);
+            second.resolve(&host)
+        }).boxed()
+    }
+}
@@ -1,66 +1,244 @@
-use futures::{failed, Future};
+use futures::{failed, finished, Future};
+use futures::stream::{self, Stream};
 use tokio_core::{LoopHandle, TcpListener, TcpStream, UdpSocket};
-use tokio_core::io::IoFuture;
+use tokio_core::io::{IoFuture, IoStream};
 
+use std::cmp;
+use std::fmt;
 use std::io;
 use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::vec;
 
 use super::select_all_ok::select_all_ok;
-use super::{Endpoint, Resolver, ToEndpoint};
+use super::select_n_ok::select_n_ok;
+use super::{Endpoint, EndpointBuf, Resolver, ToEndpoint, Transport};
 
-pub fn tcp_connect_par<'a, T, R>(handle: LoopHandle, resolver: R, ep: T) -> IoFuture<TcpStream>
-    where R: Resolver,
+pub fn tcp_connect_par<'a, X, R, T>(transport: X, resolver: R, ep: T) -> IoFuture<X::Connection>
+    where X: Transport,
+          R: Resolver,
           T: ToEndpoint<'a>,
 
 {
-    if_host_resolve(handle, resolver, ep, |handle, port, ip_addrs| {
-        debug!("creating {} parallel connection attemps", ip_addrs.len());
+    if_host_resolve(transport, resolver, ep, |transport, ep, port, ip_addrs| {
+        par_connects(transport, ep, with_port(ip_addrs, port))
+    }, |transport, addr| log_attempt(*addr, transport.connect(addr)))
+}
 
-        let futs = ip_addrs.into_iter().map(|ip_addr| {
-            let addr = SocketAddr::new(ip_addr, port);
-            handle.clone().tcp_connect(&addr)
+// Tries to connect to all of the addresses at the same time, `ep` names them in the errors.
+pub fn par_connects<X, E>(transport: X, ep: &E, addrs: Vec<SocketAddr>) -> IoFuture<X::Connection>
+    where X: Transport,
+          E: fmt::Display
+{
+    if addrs.is_empty() {
+        return failed(no_addresses(ep)).boxed();
+    }
+
+    debug!("creating {} parallel connection attemps", addrs.len());
+
+    let futs = addrs.into_iter().map(|addr| log_attempt(addr, transport.connect(&addr)));
+
+    let ep = ep.to_string();
+    select_all_ok(futs).map_err(move |_| {
+        io::Error::new(io::ErrorKind::Other, format!("all of the connections attempts to {} failed", ep))
+    }).boxed()
+}
+
+fn with_port(ip_addrs: Vec<IpAddr>, port: u16) -> Vec<SocketAddr> {
+    ip_addrs.into_iter().map(|ip_addr| SocketAddr::new(ip_addr, port)).collect()
+}
+
+pub fn tcp_connect_checked<'a, R, T, F>(handle: LoopHandle, resolver: R, ep: T, check: F) -> IoFuture<TcpStream>
+    where R: Resolver,
+          T: ToEndpoint<'a>,
+          F: Fn(TcpStream) -> IoFuture<(TcpStream, bool)> + Send + Sync + 'static
+{
+    let check = Arc::new(check);
+    let ep = match ep.to_endpoint() {
+        Ok(ep) => ep,
+        Err(e) => return failed(e).boxed(),
+    };
+
+    let resolve = resolve_endpoint(resolver, ep);
+    let ep = ep.to_buf();
+    resolve.and_then(move |addrs| {
+        if addrs.is_empty() {
+            return failed(no_addresses(&ep)).boxed();
+        }
+
+        debug!("creating {} parallel checked connection attempts", addrs.len());
+
+        let futs = addrs.into_iter().map(|addr| {
+            let check = check.clone();
+
+            log_attempt(addr, handle.clone().tcp_connect(&addr)).and_then(move |stream| {
+                check(stream)
+            }).and_then(move |(stream, passed)| {
+                if passed {
+                    Ok(stream)
+                } else {
+                    debug!("connection to {} did not pass the check", addr);
+                    Err(io::Error::new(io::ErrorKind::Other, "the connection did not pass the check"))
+                }
+            })
         });
 
-        select_all_ok(futs).map_err(|_| {
-            io::Error::new(io::ErrorKind::Other, "all of the connections attempts failed")
+        select_all_ok(futs).map_err(move |_| {
+            io::Error::new(io::ErrorKind::Other, format!("none of the connections attempts to {} passed the check", ep))
         }).boxed()
-    }, |handle, addr| handle.tcp_connect(addr))
+    }).boxed()
 }
 
-pub fn tcp_connect_seq<'a, R, T>(handle: LoopHandle, resolver: R, ep: T) -> IoFuture<TcpStream>
+pub fn tcp_connect_par_n<'a, R, T>(handle: LoopHandle, resolver: R, ep: T, n: usize)
+                                   -> IoFuture<(Vec<TcpStream>, Vec<io::Error>)>
     where R: Resolver,
           T: ToEndpoint<'a>
 {
-    if_host_resolve(handle, resolver, ep, |handle, port, ip_addrs| {
-        debug!("chaining {} connection attempts", ip_addrs.len());
+    if_host_resolve(handle, resolver, ep, move |handle, ep, port, ip_addrs| {
+        if ip_addrs.is_empty() {
+            return failed(no_addresses(ep)).boxed();
+        }
 
-        let mut prev: Option<IoFuture<TcpStream>> = None;
+        debug!("creating {} parallel connection attempts, keeping {}", ip_addrs.len(), n);
 
-        // This loop chains futures one after another so they each try
-        // to connect to an address in a sequential way.
-        for ip_addr in ip_addrs {
+        let futs = ip_addrs.into_iter().map(|ip_addr| {
             let addr = SocketAddr::new(ip_addr, port);
-            let handle = handle.clone();
+            log_attempt(addr, handle.clone().tcp_connect(&addr))
+        });
 
-            prev = Some(match prev.take() {
-                None => handle.tcp_connect(&addr).boxed(),
-                Some(prev) => prev.or_else(move |_| handle.tcp_connect(&addr)).boxed(),
-            });
+        let ep = ep.to_string();
+        select_n_ok(futs, n).map_err(move |_| {
+            io::Error::new(io::ErrorKind::Other, format!("all of the connections attempts to {} failed", ep))
+        }).boxed()
+    }, |handle, addr| {
+        log_attempt(*addr, handle.tcp_connect(addr)).map(|stream| (vec![stream], vec![])).boxed()
+    })
+}
+
+// Connects to the endpoints one after the other with `connect`, until one succeeds.
+pub fn tcp_connect_failover<'a, I, T, F>(eps: I, connect: F) -> IoFuture<TcpStream>
+    where I: IntoIterator<Item = T>,
+          T: ToEndpoint<'a>,
+          F: Fn(EndpointBuf) -> IoFuture<TcpStream> + Send + 'static
+{
+    let mut owned = Vec::new();
+    for ep in eps {
+        match ep.to_endpoint() {
+            Ok(ep) => owned.push(ep.to_buf()),
+            Err(e) => return failed(e).boxed(),
         }
+    }
 
-        // If this Option is None, it means that there were no addresses in the list.
-        match prev.take() {
-            Some(fut) => fut,
-            None => failed(io::Error::new(io::ErrorKind::Other, "resolve returned no addresses")).boxed(),
+    if owned.is_empty() {
+        return failed(io::Error::new(io::ErrorKind::Other, "no endpoints to connect to")).boxed();
+    }
+
+    fail_over(connect, owned.into_iter(), Vec::new())
+}
+
+fn fail_over<F>(connect: F, mut eps: vec::IntoIter<EndpointBuf>, mut errors: Vec<String>) -> IoFuture<TcpStream>
+    where F: Fn(EndpointBuf) -> IoFuture<TcpStream> + Send + 'static
+{
+    let ep = match eps.next() {
+        Some(ep) => ep,
+        None => {
+            let msg = format!("all of the endpoints failed: {}", errors.join("; "));
+            return failed(io::Error::new(io::ErrorKind::Other, msg)).boxed();
+        }
+    };
+
+    connect(ep.clone()).or_else(move |e| {
+        debug!("failing over from {}: {}", ep, e);
+        errors.push(format!("{}: {}", ep, e));
+        fail_over(connect, eps, errors)
+    }).boxed()
+}
+
+// Called with the outcome of each connection attempt, and the time it took when it succeeded.
+pub type Observer = Arc<Fn(SocketAddr, Result<Duration, &io::Error>) + Send + Sync>;
+
+pub fn tcp_connect_seq<'a, X, R, T>(transport: X, resolver: R, ep: T) -> IoFuture<X::Connection>
+    where X: Transport,
+          R: Resolver,
+          T: ToEndpoint<'a>
+{
+    tcp_connect_seq_observed(transport, resolver, ep, None)
+}
+
+pub fn tcp_connect_seq_observed<'a, X, R, T>(transport: X, resolver: R, ep: T, observer: Option<Observer>)
+                                             -> IoFuture<X::Connection>
+    where X: Transport,
+          R: Resolver,
+          T: ToEndpoint<'a>
+{
+    if_host_resolve(transport, resolver, ep, move |transport, ep, port, ip_addrs| {
+        chain_connects(transport, ep, with_port(ip_addrs, port), observer)
+    }, |transport, addr| log_attempt(*addr, transport.connect(addr)))
+}
+
+// The time given to the sequential connection attempts is `budget` per
+// address, up to `max`. It starts once the host is resolved, since the
+// number of addresses isn't known before.
+pub fn tcp_connect_seq_budget<'a, R, T>(handle: LoopHandle, resolver: R, ep: T, budget: Duration, max: Duration,
+                                        observer: Option<Observer>) -> IoFuture<TcpStream>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    if_host_resolve(handle, resolver, ep, move |handle, ep, port, ip_addrs| {
+        if ip_addrs.is_empty() {
+            return failed(no_addresses(ep)).boxed();
         }
-    }, |handle, addr| handle.tcp_connect(addr))
+
+        let timeout = budget.checked_mul(ip_addrs.len() as u32).map_or(max, |timeout| cmp::min(timeout, max));
+        debug!("allowing {:?} for {} connection attempts", timeout, ip_addrs.len());
+
+        with_deadline(handle.clone(), Instant::now() + timeout, move || {
+            chain_connects(handle, ep, with_port(ip_addrs, port), observer)
+        })
+    }, move |handle, addr| {
+        let addr = *addr;
+        with_deadline(handle.clone(), Instant::now() + cmp::min(budget, max), move || {
+            log_attempt(addr, handle.tcp_connect(&addr))
+        })
+    })
+}
+
+// Chains the connection attempts to the addresses one after another, `ep` names them in the errors.
+pub fn chain_connects<X, E>(transport: X, ep: &E, addrs: Vec<SocketAddr>, observer: Option<Observer>)
+                            -> IoFuture<X::Connection>
+    where X: Transport,
+          E: fmt::Display
+{
+    debug!("chaining {} connection attempts", addrs.len());
+
+    let mut prev: Option<IoFuture<X::Connection>> = None;
+
+    // This loop chains futures one after another so they each try
+    // to connect to an address in a sequential way.
+    for addr in addrs {
+        let transport = transport.clone();
+        let observer = observer.clone();
+        let attempt = move || observe_attempt(addr, log_attempt(addr, transport.connect(&addr)), observer);
+
+        prev = Some(match prev.take() {
+            None => attempt(),
+            Some(prev) => prev.or_else(move |_| attempt()).boxed(),
+        });
+    }
+
+    // If this Option is None, it means that there were no addresses in the list.
+    match prev.take() {
+        Some(fut) => fut,
+        None => failed(no_addresses(ep)).boxed(),
+    }
 }
 
 pub fn tcp_listen_seq<'a, R, T>(handle: LoopHandle, resolver: R, ep: T) -> IoFuture<TcpListener>
     where R: Resolver,
           T: ToEndpoint<'a>
 {
-    if_host_resolve(handle, resolver, ep, |handle, port, ip_addrs| {
+    if_host_resolve(handle, resolver, ep, |handle, ep, port, ip_addrs| {
         debug!("chaining {} connection attempts", ip_addrs.len());
 
         let mut prev: Option<IoFuture<TcpListener>> = None;
@@ -80,16 +258,28 @@ pub fn tcp_listen_seq<'a, R, T>(handle: LoopHandle, resolver: R, ep: T) -> IoFut
         // If this Option is None, it means that there were no addresses in the list.
         match prev.take() {
             Some(fut) => fut,
-            None => failed(io::Error::new(io::ErrorKind::Other, "resolve returned no addresses")).boxed(),
+            None => failed(no_addresses(ep)).boxed(),
         }
     }, |handle, addr| handle.tcp_listen(addr))
 }
 
+// The endpoint is normally the host with port 0, it is only different once rewritten.
+pub fn tcp_listen_ephemeral<'a, R, T>(handle: LoopHandle, resolver: R, ep: T) -> IoFuture<(TcpListener, SocketAddr)>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    tcp_listen_seq(handle, resolver, ep).and_then(|listener| {
+        let addr = try!(listener.local_addr());
+        debug!("listening on ephemeral address {}", addr);
+        Ok((listener, addr))
+    }).boxed()
+}
+
 pub fn udp_bind_seq<'a, R, T>(handle: LoopHandle, resolver: R, ep: T) -> IoFuture<UdpSocket>
     where R: Resolver,
           T: ToEndpoint<'a>
 {
-    if_host_resolve(handle, resolver, ep, |handle, port, ip_addrs| {
+    if_host_resolve(handle, resolver, ep, |handle, ep, port, ip_addrs| {
         debug!("chaining {} connection attempts", ip_addrs.len());
 
         let mut prev: Option<IoFuture<UdpSocket>> = None;
@@ -109,17 +299,144 @@ pub fn udp_bind_seq<'a, R, T>(handle: LoopHandle, resolver: R, ep: T) -> IoFutur
         // If this Option is None, it means that there were no addresses in the list.
         match prev.take() {
             Some(fut) => fut,
-            None => failed(io::Error::new(io::ErrorKind::Other, "resolve returned no addresses")).boxed(),
+            None => failed(no_addresses(ep)).boxed(),
         }
     }, |handle, addr| handle.udp_bind(addr))
 }
 
+// Future which completes after the given duration.
+pub fn sleep(handle: LoopHandle, dur: Duration) -> IoFuture<()> {
+    handle.timeout(dur).and_then(|timeout| timeout).boxed()
+}
+
+// Runs the future created by `f`, failing with a `TimedOut` error if it
+// doesn't complete before the deadline. `f` isn't called if the deadline
+// has already passed.
+pub fn with_deadline<F, T>(handle: LoopHandle, deadline: Instant, f: F) -> IoFuture<T>
+    where F: FnOnce() -> IoFuture<T>,
+          T: Send + 'static
+{
+    let now = Instant::now();
+    if deadline <= now {
+        return failed(timed_out()).boxed();
+    }
+
+    let timeout = sleep(handle, deadline - now).and_then(|()| Err(timed_out()));
+    f().select(timeout).map(|(item, _)| item).map_err(|(e, _)| e).boxed()
+}
+
+// The error returned when an operation doesn't complete in time.
+pub fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "operation timed out")
+}
+
+// The error returned when the resolution of a host yields no addresses.
+pub fn no_addresses<E>(ep: &E) -> io::Error
+    where E: fmt::Display
+{
+    io::Error::new(io::ErrorKind::Other, format!("resolve returned no addresses for {}", ep))
+}
+
+// Resolves the endpoint into the list of socket addresses it designates.
+pub fn resolve_endpoint<'a, R, T>(resolver: R, ep: T) -> IoFuture<Vec<SocketAddr>>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    let ep = match ep.to_endpoint() {
+        Ok(ep) => ep,
+        Err(e) => return failed(e).boxed(),
+    };
+
+    match ep {
+        Endpoint::Host(host, port) => {
+            resolver.resolve(host).map(move |addrs| {
+                addrs.into_iter().map(|ip_addr| SocketAddr::new(ip_addr, port)).collect()
+            }).boxed()
+        }
+        Endpoint::SocketAddr(addr) => finished(vec![addr]).boxed(),
+    }
+}
+
+// Resolves the endpoint like the connect helpers do, failing if it designates no address.
+pub fn validate_endpoint<'a, R, T>(resolver: R, ep: T) -> IoFuture<Vec<SocketAddr>>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    let ep = match ep.to_endpoint() {
+        Ok(ep) => ep,
+        Err(e) => return failed(e).boxed(),
+    };
+
+    let resolve = resolve_endpoint(resolver, ep);
+    let ep = ep.to_buf();
+    resolve.and_then(move |addrs| {
+        if addrs.is_empty() {
+            Err(no_addresses(&ep))
+        } else {
+            Ok(addrs)
+        }
+    }).boxed()
+}
+
+// Resolves the endpoint into a stream of the socket addresses it designates.
+pub fn resolve_endpoint_stream<'a, R, T>(resolver: R, ep: T) -> IoStream<SocketAddr>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    let ep = match ep.to_endpoint() {
+        Ok(ep) => ep,
+        Err(e) => return stream::iter(vec![Err(e)]).boxed(),
+    };
+
+    match ep {
+        Endpoint::Host(host, port) => {
+            resolver.resolve_stream(host).map(move |ip_addr| SocketAddr::new(ip_addr, port)).boxed()
+        }
+        Endpoint::SocketAddr(addr) => stream::iter(vec![Ok(addr)]).boxed(),
+    }
+}
+
+// Wraps a single connection attempt so that its start and outcome are logged.
+pub fn log_attempt<S>(addr: SocketAddr, fut: IoFuture<S>) -> IoFuture<S>
+    where S: Send + 'static
+{
+    debug!("attempting connection to {}", addr);
+
+    fut.then(move |res| {
+        match res {
+            Ok(_) => debug!("connection to {} succeeded", addr),
+            Err(ref e) => debug!("connection to {} failed: {}", addr, e),
+        }
+        res
+    }).boxed()
+}
+
+// Reports the outcome of a single connection attempt to the observer, if there is one.
+fn observe_attempt<S>(addr: SocketAddr, fut: IoFuture<S>, observer: Option<Observer>) -> IoFuture<S>
+    where S: Send + 'static
+{
+    let observer = match observer {
+        Some(observer) => observer,
+        None => return fut,
+    };
+
+    let start = Instant::now();
+    fut.then(move |res| {
+        match res {
+            Ok(_) => observer(addr, Ok(start.elapsed())),
+            Err(ref e) => observer(addr, Err(e)),
+        }
+        res
+    }).boxed()
+}
+
 // abstraction of the code that is common to tcp_connect_(par|seq).
-fn if_host_resolve<'a, R, T, F, E, S>(handle: LoopHandle, resolver: R, ep: T, func: F, elsef: E) -> IoFuture<S>
-        where R: Resolver,
+fn if_host_resolve<'a, H, R, T, F, E, S>(handle: H, resolver: R, ep: T, func: F, elsef: E) -> IoFuture<S>
+        where H: Send + 'static,
+              R: Resolver,
               T: ToEndpoint<'a>,
-              F: FnOnce(LoopHandle, u16, Vec<IpAddr>) -> IoFuture<S> + Send + 'static,
-              E: FnOnce(LoopHandle, &SocketAddr) -> IoFuture<S> + Send + 'static,
+              F: FnOnce(H, &Endpoint, u16, Vec<IpAddr>) -> IoFuture<S> + Send + 'static,
+              E: FnOnce(H, &SocketAddr) -> IoFuture<S> + Send + 'static,
               S: Send + 'static,
 {
     let ep = match ep.to_endpoint() {
@@ -129,8 +446,12 @@ fn if_host_resolve<'a, R, T, F, E, S>(handle: LoopHandle, resolver: R, ep: T, fu
 
     match ep {
         Endpoint::Host(host, port) => {
-            resolver.resolve(host).and_then(move |addrs| {
-                func(handle, port, addrs)
+            // The endpoint is kept so it can be named in the errors.
+            let fut = resolver.resolve(host);
+            let ep = EndpointBuf::Host(host.to_owned(), port);
+
+            fut.and_then(move |addrs| {
+                func(handle, &ep.as_endpoint(), port, addrs)
             }).boxed()
         }
         Endpoint::SocketAddr(ref addr) => {
@@ -138,3 +459,65 @@ fn if_host_resolve<'a, R, T, F, E, S>(handle: LoopHandle, resolver: R, ep: T, fu
         }
     }
 }
+
+#[test]
+fn test_connect_seq_snapshot() {
+    use std::net::Ipv4Addr;
+    use std::sync::{Arc, Mutex};
+
+    // Every resolution returns new addresses.
+    #[derive(Clone)]
+    struct Changing(Arc<Mutex<u8>>);
+
+    impl Resolver for Changing {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            let mut generation = self.0.lock().unwrap();
+            *generation += 1;
+            finished(vec![IpAddr::V4(Ipv4Addr::new(10, 0, *generation, 1)),
+                          IpAddr::V4(Ipv4Addr::new(10, 0, *generation, 2))]).boxed()
+        }
+    }
+
+    // Resolves again before every attempt, like a cache refreshing mid-flight.
+    #[derive(Clone)]
+    struct Refreshing(Changing, Arc<Mutex<Vec<SocketAddr>>>);
+
+    impl Transport for Refreshing {
+        type Connection = SocketAddr;
+
+        fn connect(&self, addr: &SocketAddr) -> IoFuture<SocketAddr> {
+            self.0.resolve("example.com").wait().unwrap();
+            self.1.lock().unwrap().push(*addr);
+            failed(io::Error::new(io::ErrorKind::ConnectionRefused, "refused")).boxed()
+        }
+    }
+
+    let resolver = Changing(Arc::new(Mutex::new(0)));
+    let attempts = Arc::new(Mutex::new(Vec::new()));
+    let transport = Refreshing(resolver.clone(), attempts.clone());
+
+    assert!(tcp_connect_seq(transport, resolver, ("example.com", 80)).wait().is_err());
+    assert_eq!(*attempts.lock().unwrap(), vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 1, 1)), 80),
+                                               SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 1, 2)), 80)]);
+}
+
+#[test]
+fn test_connect_seq_budget_no_addresses() {
+    use tokio_core::Loop;
+
+    struct Nothing;
+
+    impl Resolver for Nothing {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished(vec![]).boxed()
+        }
+    }
+
+    let lp = Loop::new().unwrap();
+    let budget = Duration::from_secs(1);
+    let fut = tcp_connect_seq_budget(lp.handle(), Nothing, ("example.com", 80), budget, budget * 4, None);
+    match fut.wait() {
+        Ok(_) => panic!("connected without addresses"),
+        Err(e) => assert_eq!(e.to_string(), "resolve returned no addresses for example.com:80"),
+    }
+}
@@ -1,17 +1,53 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{failed, finished, Future};
+use futures::stream::{self, Stream};
 use tokio_core::{LoopHandle, TcpStream, TcpListener, UdpSocket};
-use tokio_core::io::IoFuture;
+use tokio_core::io::{IoFuture, IoStream};
 
-use super::{DnsSupport, Resolver, ToEndpoint};
-use super::common::{tcp_connect_par, tcp_connect_seq, tcp_listen_seq, udp_bind_seq};
+use super::{ConnectLatencies, ConnectStats, ConnectTimings, DnsSupport, Endpoint, Ipv4Mapping, Resolver,
+            ProxyHeader, SocketOptions, StickyAddresses, Strategy, ToEndpoint};
+use super::addresses::addresses;
+use super::buffered::{BufStream, DEFAULT_CAPACITY};
+use super::candidates::tcp_connect_candidates;
+use super::common::{tcp_connect_par, tcp_connect_par_n, tcp_connect_checked, tcp_connect_seq_observed, tcp_listen_seq,
+                     tcp_listen_ephemeral, udp_bind_seq};
+use super::common::{tcp_connect_seq_budget, tcp_connect_failover, validate_endpoint, with_deadline, Observer};
+use super::hedged::tcp_connect_hedged;
+use super::http_proxy::via_http_proxy;
+use super::many::tcp_connect_many;
+use super::mapped::unmap;
+use super::proxy_protocol::with_proxy_header;
+use super::rewrite::{EndpointRewriter, Rewritten};
+use super::serve::tcp_serve;
+use super::socks4::via_socks4;
+use super::strategy::tcp_connect_strategy;
+use super::timings::tcp_connect_timed;
+use super::watch::watch_resolution;
 
 /// A helper for creating connections.
 ///
 /// This object is a wrapper around a `LoopHandle` and a resolver.
 /// It helps initiate connections using endpoints, and offers
 /// bultin address translation.
+#[derive(Clone)]
 pub struct Connector<R> where R: Clone + Resolver {
     handle: LoopHandle,
     resolver: R,
+    ipv6: bool,
+    ipv4_mapping: Option<Ipv4Mapping>,
+    stats: Option<ConnectStats>,
+    sticky: Option<StickyAddresses>,
+    latencies: Option<ConnectLatencies>,
+    literal_only: bool,
+    max_addresses: Option<usize>,
+    options: SocketOptions,
+    rewriter: Option<Arc<EndpointRewriter + Send + Sync>>,
+    buffer_sizes: (usize, usize),
 }
 
 impl<R> Connector<R> where R: Clone + Resolver {
@@ -24,32 +60,697 @@ impl<R> Connector<R> where R: Clone + Resolver {
         Connector {
             handle: handle,
             resolver: resolver,
+            ipv6: true,
+            ipv4_mapping: None,
+            stats: None,
+            sticky: None,
+            latencies: None,
+            literal_only: false,
+            max_addresses: None,
+            options: SocketOptions::default(),
+            rewriter: None,
+            buffer_sizes: (DEFAULT_CAPACITY, DEFAULT_CAPACITY),
+        }
+    }
+
+    /// Enable or disable IPv6.
+    ///
+    /// When IPv6 is disabled, the IPv6 addresses returned by the resolver
+    /// are discarded, so they are never tried. Socket addresses given
+    /// directly as endpoints are not affected. IPv6 is enabled by default.
+    pub fn ipv6(mut self, enabled: bool) -> Self {
+        self.ipv6 = enabled;
+        self
+    }
+
+    /// Set the representation of IPv4 addresses.
+    ///
+    /// The addresses returned by the resolver are turned into the given
+    /// representation before they are tried, and the duplicates this
+    /// creates are removed. Disabling IPv6 never discards IPv4-mapped
+    /// IPv6 addresses, since they designate IPv4 hosts. By default, the
+    /// addresses are left as returned by the resolver.
+    pub fn ipv4_mapping(mut self, mapping: Ipv4Mapping) -> Self {
+        self.ipv4_mapping = Some(mapping);
+        self
+    }
+
+    /// Use connection statistics.
+    ///
+    /// The outcome of the connection attempts made by `tcp_connect_seq` is
+    /// recorded in `stats`, and the resolved addresses are tried from the
+    /// most reliable to the least reliable. The statistics can be shared
+    /// by several connectors.
+    pub fn stats(mut self, stats: ConnectStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Prefer the addresses with the lowest connect times.
+    ///
+    /// The time taken by the connections established by `tcp_connect_seq`
+    /// is recorded in `latencies`, and the resolved addresses are tried from
+    /// the fastest to the slowest. Addresses without a known connect time
+    /// are tried between the fast and the slow ones. The latencies can be
+    /// shared by several connectors.
+    pub fn latencies(mut self, latencies: ConnectLatencies) -> Self {
+        self.latencies = Some(latencies);
+        self
+    }
+
+    /// Prefer the addresses that worked last time.
+    ///
+    /// The address that `tcp_connect_seq` last connected to for a host name
+    /// is remembered in `sticky`, and it is tried first the next time the
+    /// host name is resolved, before the other addresses. It is forgotten
+    /// when a connection attempt to it fails. The memory can be shared by
+    /// several connectors.
+    pub fn sticky(mut self, sticky: StickyAddresses) -> Self {
+        self.sticky = Some(sticky);
+        self
+    }
+
+    /// Accept only IP addresses, never resolve host names.
+    ///
+    /// When enabled, the resolver is never consulted: an endpoint whose
+    /// host is an IP literal is connected to like a socket address, and
+    /// any other host name fails with an error of kind `PermissionDenied`.
+    /// This guarantees that no name resolution happens. It is disabled
+    /// by default.
+    pub fn literal_only(mut self, enabled: bool) -> Self {
+        self.literal_only = enabled;
+        self
+    }
+
+    /// Limit the number of addresses tried for a host name.
+    ///
+    /// Only the first `max` addresses resolved are kept, after they are
+    /// filtered and reordered by the other options, so the connection
+    /// strategies never go beyond them. This suits `tcp_connect_seq` with
+    /// a known number of attempts, when a host has many more addresses than
+    /// that. The addresses aren't streamed when a limit is set. There is no
+    /// limit by default.
+    pub fn max_addresses(mut self, max: usize) -> Self {
+        self.max_addresses = Some(max);
+        self
+    }
+
+    /// Set the read and write timeouts of the connections created.
+    ///
+    /// The timeouts are set with `set_socket_timeouts` before a connection
+    /// is returned, so they only affect blocking system calls made on the
+    /// socket, not its use by the event loop. They aren't set by default.
+    /// A zero duration makes the connections fail with an error of kind
+    /// `InvalidInput`.
+    ///
+    /// This method is only available on Unix platforms.
+    #[cfg(unix)]
+    pub fn socket_timeouts(mut self, read: Option<Duration>, write: Option<Duration>) -> Self {
+        self.options = self.options.socket_timeouts(read, write);
+        self
+    }
+
+    /// Set the DSCP marking of the connections created, after their handshake.
+    ///
+    /// The marking is set with `set_dscp` once a connection is established,
+    /// from `IP_TOS` or `IPV6_TCLASS` depending on the address family of
+    /// the peer. It only applies to the packets sent after the handshake:
+    /// the SYN and the rest of the handshake are never marked, since the
+    /// option can't be set before the connect. A value greater than 63
+    /// makes the connections fail with an error of kind `InvalidInput`. It
+    /// isn't set by default.
+    ///
+    /// This method is only available on Unix platforms.
+    #[cfg(unix)]
+    pub fn dscp(mut self, dscp: u8) -> Self {
+        self.options = self.options.dscp(dscp);
+        self
+    }
+
+    /// Enable or disable Nagle's algorithm on the connections created.
+    ///
+    /// This sets `TCP_NODELAY` before a connection is returned. It is left
+    /// to the system default unless this method is called.
+    pub fn nodelay(mut self, enabled: bool) -> Self {
+        self.options = self.options.nodelay(enabled);
+        self
+    }
+
+    /// Enable or disable the quick acknowledgement mode on the connections created.
+    ///
+    /// This sets `TCP_QUICKACK` with `set_quickack` before a connection is
+    /// returned. Together with `nodelay(true)`, it lowers the latency of a
+    /// request-response exchange right after the connect. It is left to
+    /// the system default unless this method is called.
+    ///
+    /// This method is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn quickack(mut self, enabled: bool) -> Self {
+        self.options = self.options.quickack(enabled);
+        self
+    }
+
+    /// Rewrite the endpoints before they are resolved.
+    ///
+    /// Every endpoint given to the connect, listen and bind methods is
+    /// passed to `rewriter` once converted, and the endpoint it returns,
+    /// if any, is used instead. `tcp_listen_ephemeral` passes its host with
+    /// port 0. The target of `tcp_connect_via_http_proxy`, resolved by the
+    /// proxy, isn't rewritten.
+    pub fn rewriter<W>(mut self, rewriter: W) -> Self
+        where W: EndpointRewriter + Send + Sync + 'static
+    {
+        self.rewriter = Some(Arc::new(rewriter));
+        self
+    }
+
+    /// Set the capacities of the read and write buffers of `tcp_connect_buffered`.
+    ///
+    /// Both are 8 KiB by default.
+    pub fn buffer_sizes(mut self, read: usize, write: usize) -> Self {
+        self.buffer_sizes = (read, write);
+        self
+    }
+
+    /// Set all of the socket options of the connections created at once.
+    ///
+    /// This replaces the options set by `socket_timeouts`, `dscp`,
+    /// `nodelay`, `quickack` and `user_timeout`.
+    pub fn socket_options(mut self, options: SocketOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn rewrite<'a, T>(&self, ep: T) -> Rewritten<'a>
+        where T: ToEndpoint<'a>
+    {
+        Rewritten::new(ep, self.rewriter.as_ref())
+    }
+
+    /// Set the user timeout of the connections created.
+    ///
+    /// This sets `TCP_USER_TIMEOUT` with `set_user_timeout` before a
+    /// connection is returned: it is how long the data sent may remain
+    /// unacknowledged before the connection is dropped. It is left to the
+    /// system default unless this method is called. The option only exists
+    /// on Linux: elsewhere, the connect methods fail with an error instead
+    /// of ignoring it, before anything is resolved or connected to.
+    pub fn user_timeout(mut self, timeout: Duration) -> Self {
+        self.options = self.options.user_timeout(timeout);
+        self
+    }
+
+    // Creates the connection with `connect` and sets the options on it,
+    // unless they can't be set on this platform.
+    fn apply_options<F>(&self, connect: F) -> IoFuture<TcpStream>
+        where F: FnOnce() -> IoFuture<TcpStream>
+    {
+        if let Err(e) = self.options.check() {
+            return failed(e).boxed();
+        }
+
+        let fut = connect();
+        if self.options == SocketOptions::default() {
+            return fut;
+        }
+
+        let options = self.options;
+        fut.and_then(move |stream| options.apply(&stream).map(|()| stream)).boxed()
+    }
+
+    fn resolver(&self) -> ConnectorResolver<R> {
+        ConnectorResolver {
+            resolver: self.resolver.clone(),
+            ipv6: self.ipv6,
+            ipv4_mapping: self.ipv4_mapping,
+            stats: self.stats.clone(),
+            sticky: self.sticky.clone(),
+            latencies: self.latencies.clone(),
+            literal_only: self.literal_only,
+            max_addresses: self.max_addresses,
+        }
+    }
+
+    fn observer(&self, ep: &Endpoint) -> Option<Observer> {
+        let stats = self.stats.clone();
+        let latencies = self.latencies.clone();
+        let sticky = match *ep {
+            Endpoint::Host(host, _) => self.sticky.clone().map(|sticky| (sticky, host.to_owned())),
+            Endpoint::SocketAddr(_) => None,
+        };
+
+        if stats.is_none() && latencies.is_none() && sticky.is_none() {
+            return None;
+        }
+
+        Some(Arc::new(move |addr: SocketAddr, res: Result<_, &io::Error>| {
+            if let Some(ref stats) = stats {
+                stats.record(addr.ip(), res.is_ok());
+            }
+            if let Some(ref latencies) = latencies {
+                if let Ok(time) = res {
+                    latencies.record(addr.ip(), time);
+                }
+            }
+            if let Some((ref sticky, ref host)) = sticky {
+                if res.is_ok() {
+                    sticky.record_success(host, addr.ip());
+                } else {
+                    sticky.record_failure(host, addr.ip());
+                }
+            }
+        }))
+    }
+}
+
+// Applies the settings of the connector to the addresses returned by the resolver.
+struct ConnectorResolver<R> {
+    resolver: R,
+    ipv6: bool,
+    ipv4_mapping: Option<Ipv4Mapping>,
+    stats: Option<ConnectStats>,
+    sticky: Option<StickyAddresses>,
+    latencies: Option<ConnectLatencies>,
+    literal_only: bool,
+    max_addresses: Option<usize>,
+}
+
+impl<R> Resolver for ConnectorResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        if self.literal_only {
+            let literal = host.trim_left_matches('[').trim_right_matches(']');
+            return match IpAddr::from_str(literal) {
+                Ok(addr) => finished(vec![addr]).boxed(),
+                Err(_) => {
+                    let msg = format!("{} is not an IP address, and resolution is disabled", host);
+                    failed(io::Error::new(io::ErrorKind::PermissionDenied, msg)).boxed()
+                }
+            };
+        }
+
+        let fut = self.resolver.resolve(host);
+
+        if self.ipv6 && self.ipv4_mapping.is_none() && self.stats.is_none() && self.latencies.is_none() &&
+           self.sticky.is_none() && self.max_addresses.is_none() {
+            return fut;
         }
+
+        let ipv6 = self.ipv6;
+        let ipv4_mapping = self.ipv4_mapping;
+        let stats = self.stats.clone();
+        let latencies = self.latencies.clone();
+        let sticky = self.sticky.clone();
+        let max_addresses = self.max_addresses;
+        let host = host.to_owned();
+
+        fut.map(move |addrs| {
+            let mut addrs: Vec<IpAddr> = addrs.into_iter().filter(|&addr| match unmap(addr) {
+                IpAddr::V4(_) => true,
+                IpAddr::V6(_) => ipv6,
+            }).collect();
+
+            if let Some(mapping) = ipv4_mapping {
+                let mut mapped = Vec::with_capacity(addrs.len());
+                for addr in addrs {
+                    let addr = mapping.apply(addr);
+                    if !mapped.contains(&addr) {
+                        mapped.push(addr);
+                    }
+                }
+                addrs = mapped;
+            }
+
+            if let Some(stats) = stats {
+                stats.sort(&mut addrs);
+            }
+
+            if let Some(latencies) = latencies {
+                latencies.sort(&mut addrs);
+            }
+
+            if let Some(sticky) = sticky {
+                sticky.promote(&host, &mut addrs);
+            }
+
+            if let Some(max) = max_addresses {
+                addrs.truncate(max);
+            }
+
+            addrs
+        }).boxed()
+    }
+
+    fn resolve_stream(&self, host: &str) -> IoStream<IpAddr> {
+        // The addresses can only be streamed when they don't have to be
+        // deduplicated, reordered or limited, which needs all of them.
+        if self.literal_only || self.ipv4_mapping.is_some() || self.stats.is_some() || self.latencies.is_some() ||
+           self.sticky.is_some() || self.max_addresses.is_some() {
+            return addresses(self.resolve(host));
+        }
+
+        let ipv6 = self.ipv6;
+        self.resolver.resolve_stream(host).filter(move |&addr| match unmap(addr) {
+            IpAddr::V4(_) => true,
+            IpAddr::V6(_) => ipv6,
+        }).boxed()
     }
 }
 
-impl<R> DnsSupport for Connector<R> where R: Clone + Resolver {
+impl<R> Connector<R> where R: Clone + Resolver + Send + 'static {
+    /// Create a new buffered stream connected to the specified endpoint.
+    ///
+    /// The endpoint is connected to like in `tcp_connect_seq`, and the
+    /// stream is wrapped in a `BufStream` with the capacities set by
+    /// `buffer_sizes`.
+    pub fn tcp_connect_buffered<'a, T>(&self, ep: T) -> IoFuture<BufStream<TcpStream>>
+        where T: ToEndpoint<'a>
+    {
+        let (read, write) = self.buffer_sizes;
+        self.tcp_connect_seq(ep).map(move |stream| BufStream::with_capacities(read, write, stream)).boxed()
+    }
+}
+
+impl<R> DnsSupport for Connector<R> where R: Clone + Resolver + Send + 'static {
     fn tcp_connect_par<'a, T>(&self, ep: T) -> IoFuture<TcpStream>
         where T: ToEndpoint<'a>
     {
-        tcp_connect_par(self.handle.clone(), self.resolver.clone(), ep)
+        self.apply_options(|| tcp_connect_par(self.handle.clone(), self.resolver(), &self.rewrite(ep)))
+    }
+
+    fn tcp_connect_checked<'a, T, F>(&self, ep: T, check: F) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>,
+              F: Fn(TcpStream) -> IoFuture<(TcpStream, bool)> + Send + Sync + 'static
+    {
+        self.apply_options(|| tcp_connect_checked(self.handle.clone(), self.resolver(), &self.rewrite(ep), check))
+    }
+
+    fn tcp_connect_par_n<'a, T>(&self, ep: T, n: usize) -> IoFuture<(Vec<TcpStream>, Vec<io::Error>)>
+        where T: ToEndpoint<'a>
+    {
+        if let Err(e) = self.options.check() {
+            return failed(e).boxed();
+        }
+
+        let fut = tcp_connect_par_n(self.handle.clone(), self.resolver(), &self.rewrite(ep), n);
+        if self.options == SocketOptions::default() {
+            return fut;
+        }
+
+        let options = self.options;
+        fut.and_then(move |(streams, errors)| {
+            for stream in &streams {
+                try!(options.apply(stream));
+            }
+            Ok((streams, errors))
+        }).boxed()
     }
 
     fn tcp_connect_seq<'a, T>(&self, ep: T) -> IoFuture<TcpStream>
         where T: ToEndpoint<'a>
     {
-        tcp_connect_seq(self.handle.clone(), self.resolver.clone(), ep)
+        let rewritten = self.rewrite(ep);
+        let ep = match (&rewritten).to_endpoint() {
+            Ok(ep) => ep,
+            Err(e) => return failed(e).boxed(),
+        };
+
+        let observer = self.observer(&ep);
+        self.apply_options(|| tcp_connect_seq_observed(self.handle.clone(), self.resolver(), ep, observer))
+    }
+
+    fn tcp_connect_hedged<'a, T>(&self, ep: T, delay: Duration) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        self.apply_options(|| tcp_connect_hedged(self.handle.clone(), self.resolver(), &self.rewrite(ep), delay))
+    }
+
+    fn tcp_connect_strategy<'a, T>(&self, ep: T, strategy: Strategy) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        self.apply_options(|| tcp_connect_strategy(self.handle.clone(), self.resolver(), &self.rewrite(ep), strategy))
+    }
+
+    fn tcp_connect_timed<'a, T>(&self, ep: T) -> IoFuture<(TcpStream, ConnectTimings)>
+        where T: ToEndpoint<'a>
+    {
+        let rewritten = self.rewrite(ep);
+        let ep = match (&rewritten).to_endpoint() {
+            Ok(ep) => ep,
+            Err(e) => return failed(e).boxed(),
+        };
+
+        if let Err(e) = self.options.check() {
+            return failed(e).boxed();
+        }
+
+        let observer = self.observer(&ep);
+        let fut = tcp_connect_timed(self.handle.clone(), self.resolver(), ep, observer);
+        if self.options == SocketOptions::default() {
+            return fut;
+        }
+
+        let options = self.options;
+        fut.and_then(move |(stream, timings)| {
+            try!(options.apply(&stream));
+            Ok((stream, timings))
+        }).boxed()
+    }
+
+    fn tcp_connect_failover<'a, I, T>(&self, eps: I) -> IoFuture<TcpStream>
+        where I: IntoIterator<Item = T>,
+              T: ToEndpoint<'a>
+    {
+        let connector = self.clone();
+        tcp_connect_failover(eps, move |ep| connector.tcp_connect_seq(&ep))
+    }
+
+    fn tcp_connect_many<'a, I, T>(&self, eps: I, limit: usize) -> IoFuture<Vec<io::Result<TcpStream>>>
+        where I: IntoIterator<Item = T>,
+              T: ToEndpoint<'a>
+    {
+        let connector = self.clone();
+        tcp_connect_many(eps, limit, move |ep| connector.tcp_connect_seq(&ep))
+    }
+
+    fn tcp_connect_par_deadline<'a, T>(&self, ep: T, deadline: Instant) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        with_deadline(self.handle.clone(), deadline, || self.tcp_connect_par(ep))
+    }
+
+    fn tcp_connect_seq_deadline<'a, T>(&self, ep: T, deadline: Instant) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        with_deadline(self.handle.clone(), deadline, || self.tcp_connect_seq(ep))
+    }
+
+    fn tcp_connect_seq_budget<'a, T>(&self, ep: T, budget: Duration, max: Duration) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        let rewritten = self.rewrite(ep);
+        let ep = match (&rewritten).to_endpoint() {
+            Ok(ep) => ep,
+            Err(e) => return failed(e).boxed(),
+        };
+
+        let observer = self.observer(&ep);
+        self.apply_options(|| tcp_connect_seq_budget(self.handle.clone(), self.resolver(), ep, budget, max, observer))
+    }
+
+    fn tcp_connect_via_http_proxy<'a, 'b, P, T>(&self, proxy: P, target: T, authorization: Option<&str>)
+                                                -> IoFuture<TcpStream>
+        where P: ToEndpoint<'a>,
+              T: ToEndpoint<'b>
+    {
+        via_http_proxy(self.tcp_connect_seq(proxy), target, authorization)
+    }
+
+    fn tcp_connect_via_socks4<'a, 'b, P, T>(&self, proxy: P, target: T, userid: Option<&str>) -> IoFuture<TcpStream>
+        where P: ToEndpoint<'a>,
+              T: ToEndpoint<'b>
+    {
+        via_socks4(self.tcp_connect_seq(proxy), target, userid)
+    }
+
+    fn tcp_connect_with_proxy_header<'a, T>(&self, ep: T, header: &ProxyHeader) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        with_proxy_header(self.tcp_connect_seq(ep), header)
+    }
+
+    fn tcp_connect_candidates<'a, T>(&self, ep: T) -> IoStream<(SocketAddr, io::Result<TcpStream>)>
+        where T: ToEndpoint<'a>
+    {
+        if let Err(e) = self.options.check() {
+            return stream::iter(vec![Err(e)]).boxed();
+        }
+
+        let candidates = tcp_connect_candidates(self.handle.clone(), self.resolver(), &self.rewrite(ep));
+        if self.options == SocketOptions::default() {
+            return candidates;
+        }
+
+        // A candidate whose options can't be set is reported as failed.
+        let options = self.options;
+        candidates.map(move |(addr, res)| {
+            (addr, res.and_then(|stream| options.apply(&stream).map(|()| stream)))
+        }).boxed()
     }
 
     fn tcp_listen_seq<'a, T>(&self, ep: T) -> IoFuture<TcpListener>
         where T: ToEndpoint<'a>
     {
-        tcp_listen_seq(self.handle.clone(), self.resolver.clone(), ep)
+        tcp_listen_seq(self.handle.clone(), self.resolver(), &self.rewrite(ep))
+    }
+
+    fn tcp_listen_ephemeral(&self, host: &str) -> IoFuture<(TcpListener, SocketAddr)> {
+        tcp_listen_ephemeral(self.handle.clone(), self.resolver(), &self.rewrite((host, 0)))
+    }
+
+    fn tcp_serve<'a, T>(&self, ep: T) -> IoStream<(TcpStream, SocketAddr)>
+        where T: ToEndpoint<'a>
+    {
+        tcp_serve(self.handle.clone(), self.resolver(), &self.rewrite(ep))
     }
 
     fn udp_bind_seq<'a, T>(&self, ep: T) -> IoFuture<UdpSocket>
         where T: ToEndpoint<'a>
     {
-        udp_bind_seq(self.handle.clone(), self.resolver.clone(), ep)
+        udp_bind_seq(self.handle.clone(), self.resolver(), &self.rewrite(ep))
+    }
+
+    fn validate_endpoint<'a, T>(&self, ep: T) -> IoFuture<Vec<SocketAddr>>
+        where T: ToEndpoint<'a>
+    {
+        validate_endpoint(self.resolver(), &self.rewrite(ep))
+    }
+
+    fn watch_resolution(&self, host: &str, interval: Duration) -> IoStream<Vec<IpAddr>> {
+        watch_resolution(self.handle.clone(), self.resolver(), host, interval)
+    }
+}
+
+#[test]
+fn test_literal_only() {
+    use tokio_core::Loop;
+
+    #[derive(Clone)]
+    struct Unused;
+
+    impl Resolver for Unused {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            panic!("the resolver must not be consulted");
+        }
+    }
+
+    let lp = Loop::new().unwrap();
+    let connector = Connector::new(lp.handle(), Unused).literal_only(true);
+
+    let e = connector.validate_endpoint("example.com:80").wait().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+
+    let addrs = connector.validate_endpoint(Endpoint::Host("127.0.0.1", 80)).wait().unwrap();
+    assert_eq!(addrs, vec![SocketAddr::from_str("127.0.0.1:80").unwrap()]);
+}
+
+#[test]
+fn test_max_addresses() {
+    use std::net::Ipv4Addr;
+    use tokio_core::Loop;
+
+    #[derive(Clone)]
+    struct Many;
+
+    impl Resolver for Many {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished((0..10).map(|i| IpAddr::V4(Ipv4Addr::new(10, 0, 0, i))).collect()).boxed()
+        }
+    }
+
+    let lp = Loop::new().unwrap();
+    let connector = Connector::new(lp.handle(), Many).max_addresses(2);
+
+    let addrs = connector.validate_endpoint("example.com:80").wait().unwrap();
+    assert_eq!(addrs, vec![SocketAddr::from_str("10.0.0.0:80").unwrap(),
+                           SocketAddr::from_str("10.0.0.1:80").unwrap()]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_options_on_every_path() {
+    use std::mem;
+    use std::net;
+    use std::os::unix::io::AsRawFd;
+    use libc;
+    use tokio_core::Loop;
+    use super::CpuPoolResolver;
+
+    fn read_timeout(stream: &TcpStream) -> Duration {
+        let mut tv = libc::timeval {
+            tv_sec: 0,
+            tv_usec: 0,
+        };
+        let mut len = mem::size_of::<libc::timeval>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(stream.as_raw_fd(),
+                             libc::SOL_SOCKET,
+                             libc::SO_RCVTIMEO,
+                             &mut tv as *mut libc::timeval as *mut libc::c_void,
+                             &mut len)
+        };
+        assert_eq!(ret, 0);
+        Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000)
+    }
+
+    fn nodelay(stream: &TcpStream) -> bool {
+        let mut value: libc::c_int = 0;
+        let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(stream.as_raw_fd(),
+                             libc::IPPROTO_TCP,
+                             libc::TCP_NODELAY,
+                             &mut value as *mut libc::c_int as *mut libc::c_void,
+                             &mut len)
+        };
+        assert_eq!(ret, 0);
+        value != 0
+    }
+
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let timeout = Duration::from_secs(7);
+
+    let mut lp = Loop::new().unwrap();
+    let connector = Connector::new(lp.handle(), CpuPoolResolver::new(1))
+        .socket_timeouts(Some(timeout), None)
+        .nodelay(true);
+
+    let (streams, _) = lp.run(connector.tcp_connect_par_n(addr, 1)).unwrap();
+    assert_eq!(read_timeout(&streams[0]), timeout);
+    assert!(nodelay(&streams[0]));
+
+    let (stream, _) = lp.run(connector.tcp_connect_timed(addr)).unwrap();
+    assert_eq!(read_timeout(&stream), timeout);
+    assert!(nodelay(&stream));
+
+    let candidates = lp.run(connector.tcp_connect_candidates(addr).collect()).unwrap();
+    let stream = candidates[0].1.as_ref().ok().unwrap();
+    assert_eq!(read_timeout(stream), timeout);
+    assert!(nodelay(stream));
+}
+
+#[cfg(not(target_os = "linux"))]
+#[test]
+fn test_user_timeout_unsupported() {
+    use tokio_core::Loop;
+    use super::CpuPoolResolver;
+
+    let lp = Loop::new().unwrap();
+    let connector = Connector::new(lp.handle(), CpuPoolResolver::new(1)).user_timeout(Duration::from_secs(30));
+
+    // Nothing listens on this port, so a connection attempt would be refused instead.
+    match connector.tcp_connect_seq("127.0.0.1:1").wait() {
+        Ok(_) => panic!("connected with an unsupported option"),
+        Err(e) => assert_eq!(e.to_string(), "TCP_USER_TIMEOUT is only supported on Linux"),
     }
 }
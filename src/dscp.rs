@@ -0,0 +1,59 @@
+use std::io;
+use std::mem;
+use std::net::SocketAddr;
+use std::os::unix::io::AsRawFd;
+
+use libc;
+use tokio_core::TcpStream;
+
+// The traffic class byte holds the DSCP in its upper 6 bits, the lower 2 are for ECN.
+fn traffic_class(dscp: u8) -> io::Result<libc::c_int> {
+    if dscp > 63 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "a DSCP value must be between 0 and 63"));
+    }
+    Ok((dscp as libc::c_int) << 2)
+}
+
+/// Set the DSCP marking of the packets sent on a connection.
+///
+/// The Differentiated Services Code Point is a value between 0 and 63, a
+/// greater value is an error of kind `InvalidInput`. An expedited
+/// forwarding class for latency-sensitive traffic is 46, and a low priority
+/// class for bulk traffic is 8, for instance. It is set with `IP_TOS` for an
+/// IPv4 peer and with `IPV6_TCLASS` for an IPv6 peer. The marking is only a
+/// request: networks along the way may honor it, rewrite it or clear it.
+///
+/// The stream is already connected, so the marking only applies to the
+/// packets sent from now on. The packets of the handshake, like the SYN,
+/// went out unmarked: marking them needs the option to be set before the
+/// connect, which `LoopHandle::tcp_connect` doesn't allow.
+///
+/// This function is only available on Unix platforms.
+pub fn set_dscp(stream: &TcpStream, dscp: u8) -> io::Result<()> {
+    let value = try!(traffic_class(dscp));
+    let (level, opt) = match try!(stream.peer_addr()) {
+        SocketAddr::V4(_) => (libc::IPPROTO_IP, libc::IP_TOS),
+        SocketAddr::V6(_) => (libc::IPPROTO_IPV6, libc::IPV6_TCLASS),
+    };
+
+    let ret = unsafe {
+        libc::setsockopt(stream.as_raw_fd(),
+                         level,
+                         opt,
+                         &value as *const libc::c_int as *const libc::c_void,
+                         mem::size_of::<libc::c_int>() as libc::socklen_t)
+    };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[test]
+fn test_traffic_class() {
+    assert_eq!(traffic_class(0).unwrap(), 0);
+    assert_eq!(traffic_class(46).unwrap(), 0xb8);
+    assert_eq!(traffic_class(64).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
@@ -1,12 +1,13 @@
+use std::fmt;
 use std::io;
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
 use std::str::FromStr;
 
 /// An Endpoint is a way of identifying the target of a connection.
 ///
 /// It can be a socket address or a host name which needs to be resolved
 /// into a list of IP addresses.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum Endpoint<'a> {
     /// Endpoint is a host.
     ///
@@ -16,14 +17,104 @@ pub enum Endpoint<'a> {
     SocketAddr(SocketAddr),
 }
 
+impl<'a> Endpoint<'a> {
+    /// Get the canonical form of this endpoint.
+    ///
+    /// Host names are lowercased and stripped of a trailing dot. A host
+    /// name which is actually an IP literal becomes a `SocketAddr`, and
+    /// the flow information of IPv6 socket addresses is cleared. Two
+    /// endpoints that refer to the same target normalize equal, so the
+    /// result can be used as the key of a map.
+    pub fn normalize(&self) -> EndpointBuf {
+        match *self {
+            Endpoint::Host(host, port) => {
+                let host = if host.ends_with('.') { &host[..host.len() - 1] } else { host };
+                let literal = host.trim_left_matches('[').trim_right_matches(']');
+
+                match IpAddr::from_str(literal) {
+                    Ok(addr) => Endpoint::SocketAddr(SocketAddr::new(addr, port)).normalize(),
+                    Err(_) => EndpointBuf::Host(host.to_lowercase(), port),
+                }
+            }
+            Endpoint::SocketAddr(SocketAddr::V6(addr)) => {
+                EndpointBuf::SocketAddr(SocketAddr::V6(SocketAddrV6::new(*addr.ip(), addr.port(), 0, addr.scope_id())))
+            }
+            Endpoint::SocketAddr(addr) => EndpointBuf::SocketAddr(addr),
+        }
+    }
+
+    /// Get the port of this endpoint.
+    pub fn port(&self) -> u16 {
+        match *self {
+            Endpoint::Host(_, port) => port,
+            Endpoint::SocketAddr(ref addr) => addr.port(),
+        }
+    }
+
+    /// Get an owned copy of this endpoint.
+    pub fn to_buf(self) -> EndpointBuf {
+        match self {
+            Endpoint::Host(host, port) => EndpointBuf::Host(host.to_owned(), port),
+            Endpoint::SocketAddr(addr) => EndpointBuf::SocketAddr(addr),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Endpoint<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Endpoint::Host(ref host, port) => write!(f, "{}:{}", host, port),
+            Endpoint::SocketAddr(ref addr) => write!(f, "{}", addr),
+        }
+    }
+}
+
+/// An owned Endpoint.
+///
+/// It owns its host name, so it can be stored or moved into a future. It
+/// is created by `Endpoint::to_buf` and `Endpoint::normalize`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum EndpointBuf {
+    /// Endpoint is a host.
+    ///
+    /// The `String` is the name and the `u16` is the port.
+    Host(String, u16),
+    /// Endpoint is a `SocketAddr`.
+    SocketAddr(SocketAddr),
+}
+
+impl EndpointBuf {
+    /// Borrow this endpoint as an `Endpoint`.
+    pub fn as_endpoint<'a>(&'a self) -> Endpoint<'a> {
+        match *self {
+            EndpointBuf::Host(ref host, port) => Endpoint::Host(host, port),
+            EndpointBuf::SocketAddr(addr) => Endpoint::SocketAddr(addr),
+        }
+    }
+
+    /// Get the port of this endpoint.
+    pub fn port(&self) -> u16 {
+        self.as_endpoint().port()
+    }
+}
+
+impl fmt::Display for EndpointBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_endpoint().fmt(f)
+    }
+}
+
 /// A trait for objects that can be converted into an Endpoint.
 ///
 /// This trait is implemented for the following types:
 ///
+/// * `Endpoint` - the endpoint itself.
+/// * `&EndpointBuf` - an owned endpoint.
 /// * `SocketAddr`, `&SocketAddr` - a socket address.
 /// * `(IpAddr, u16)`, `(&str, u16)` - a target and a port.
 /// * `&str` - a string formatted as `<target>:<port>` where
 /// `<target>` is a host name or an IP address.
+/// * `AllowPorts` - an endpoint restricted to a set of ports.
 ///
 /// This trait is similar to the `ToSocketAddrs` trait, except
 /// that it does not perform host name resolution.
@@ -32,6 +123,55 @@ pub trait ToEndpoint<'a> {
     fn to_endpoint(self) -> io::Result<Endpoint<'a>>;
 }
 
+impl<'a> ToEndpoint<'a> for Endpoint<'a> {
+    fn to_endpoint(self) -> io::Result<Endpoint<'a>> {
+        Ok(self)
+    }
+}
+
+impl<'a> ToEndpoint<'a> for &'a EndpointBuf {
+    fn to_endpoint(self) -> io::Result<Endpoint<'a>> {
+        Ok(self.as_endpoint())
+    }
+}
+
+/// An endpoint restricted to a set of allowed ports.
+///
+/// This is created by the `allow_ports` function.
+pub struct AllowPorts<'p, T> {
+    ep: T,
+    ports: &'p [u16],
+}
+
+/// Restrict an endpoint to the given ports.
+///
+/// The endpoint is converted like `ep` would be, but it fails with an
+/// error of kind `PermissionDenied` if its port isn't one of `ports`.
+/// Since the connect methods convert the endpoint before resolving it,
+/// nothing is resolved or connected to when the port isn't allowed.
+/// This is a guardrail for endpoints taken from untrusted input.
+pub fn allow_ports<'a, 'p, T>(ep: T, ports: &'p [u16]) -> AllowPorts<'p, T>
+    where T: ToEndpoint<'a>
+{
+    AllowPorts {
+        ep: ep,
+        ports: ports,
+    }
+}
+
+impl<'a, 'p, T> ToEndpoint<'a> for AllowPorts<'p, T> where T: ToEndpoint<'a> {
+    fn to_endpoint(self) -> io::Result<Endpoint<'a>> {
+        let ep = try!(self.ep.to_endpoint());
+
+        if !self.ports.contains(&ep.port()) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied,
+                                      format!("port {} of {} is not allowed", ep.port(), ep)));
+        }
+
+        Ok(ep)
+    }
+}
+
 impl<'a> ToEndpoint<'a> for SocketAddr {
     fn to_endpoint(self) -> io::Result<Endpoint<'a>> {
         Ok(Endpoint::SocketAddr(self))
@@ -156,3 +296,41 @@ fn test_endpoint_str_ipv6() {
         _ => panic!(),
     }
 }
+
+#[test]
+fn test_endpoint_normalize_host() {
+    let a = "Example.COM.:80".to_endpoint().unwrap().normalize();
+    let b = ("example.com", 80).to_endpoint().unwrap().normalize();
+    assert_eq!(a, b);
+    assert_eq!(a, EndpointBuf::Host("example.com".to_owned(), 80));
+}
+
+#[test]
+fn test_endpoint_normalize_ipv6() {
+    use std::collections::HashSet;
+
+    let a = "::1:1227".to_endpoint().unwrap().normalize();
+    let b = "[0:0:0:0:0:0:0:1]:1227".to_endpoint().unwrap().normalize();
+    assert_eq!(a, b);
+
+    let mut set = HashSet::new();
+    set.insert(a);
+    set.insert(b);
+    assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn test_endpoint_display() {
+    assert_eq!("localhost:1227".to_endpoint().unwrap().to_string(), "localhost:1227");
+    assert_eq!("[::1]:1227".to_endpoint().unwrap().to_string(), "[::1]:1227");
+}
+
+#[test]
+fn test_allow_ports() {
+    let ports = [443, 8443];
+    assert_eq!(allow_ports("localhost:8443", &ports).to_endpoint().unwrap(),
+               Endpoint::Host("localhost", 8443));
+
+    let e = allow_ports("localhost:80", &ports).to_endpoint().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+}
@@ -0,0 +1,91 @@
+use std::env;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use futures::{finished, Future};
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A resolver that reads overrides from environment variables.
+///
+/// The name of the variable of a host name is the prefix followed by the
+/// host name, with every character other than a letter or a digit replaced
+/// by an underscore. For instance, the addresses of `example.com` are read
+/// from `RESOLVE_example_com` with the default prefix. The variable holds
+/// comma-separated IP addresses. Invalid addresses are logged and skipped.
+///
+/// When the variable isn't set or holds no valid address, the host name is
+/// given to the inner resolver.
+#[derive(Clone)]
+pub struct EnvResolver<R> {
+    resolver: R,
+    prefix: String,
+}
+
+impl<R> EnvResolver<R> where R: Resolver {
+    /// Create a new EnvResolver reading variables prefixed by `RESOLVE_`.
+    pub fn new(resolver: R) -> Self {
+        EnvResolver::with_prefix(resolver, "RESOLVE_")
+    }
+
+    /// Create a new EnvResolver reading variables with the given prefix.
+    pub fn with_prefix(resolver: R, prefix: &str) -> Self {
+        EnvResolver {
+            resolver: resolver,
+            prefix: prefix.to_owned(),
+        }
+    }
+
+    fn var_name(&self, host: &str) -> String {
+        let host = host.chars().map(|c| if c.is_alphanumeric() { c } else { '_' });
+        self.prefix.chars().chain(host).collect()
+    }
+}
+
+impl<R> Resolver for EnvResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let name = self.var_name(host);
+
+        if let Ok(value) = env::var(&name) {
+            let mut addrs = Vec::new();
+            for part in value.split(',').map(|part| part.trim()).filter(|part| !part.is_empty()) {
+                match IpAddr::from_str(part) {
+                    Ok(addr) => addrs.push(addr),
+                    Err(_) => warn!("invalid address in {}: {}", name, part),
+                }
+            }
+
+            if !addrs.is_empty() {
+                debug!("resolved {} from {}", host, name);
+                return finished(addrs).boxed();
+            }
+        }
+
+        self.resolver.resolve(host)
+    }
+}
+
+#[test]
+fn test_env() {
+    use std::io;
+    use std::net::Ipv4Addr;
+    use futures::failed;
+
+    struct Failing;
+
+    impl Resolver for Failing {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            failed(io::Error::new(io::ErrorKind::Other, "failing")).boxed()
+        }
+    }
+
+    let resolver = EnvResolver::with_prefix(Failing, "TOKIO_DNS_TEST_");
+    env::set_var("TOKIO_DNS_TEST_one_example_com", "10.0.0.1, bogus,10.0.0.2");
+    env::set_var("TOKIO_DNS_TEST_two_example_com", "bogus");
+
+    assert_eq!(resolver.resolve("one.example.com").wait().unwrap(),
+               vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))]);
+    assert!(resolver.resolve("two.example.com").wait().is_err());
+    assert!(resolver.resolve("three.example.com").wait().is_err());
+}
@@ -0,0 +1,79 @@
+use std::net::IpAddr;
+
+use futures::Future;
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A resolver that falls back to static addresses.
+///
+/// The fallback addresses are returned when the inner resolver fails or
+/// returns no addresses, so a host with a known, stable address remains
+/// reachable during an outage of name resolution. They are never used
+/// when the inner resolver returns at least one address.
+#[derive(Clone)]
+pub struct WithFallbackResolver<R> {
+    resolver: R,
+    fallback: Vec<IpAddr>,
+}
+
+impl<R> WithFallbackResolver<R> where R: Resolver {
+    /// Create a new WithFallbackResolver falling back to the given addresses.
+    pub fn new(resolver: R, fallback: Vec<IpAddr>) -> Self {
+        WithFallbackResolver {
+            resolver: resolver,
+            fallback: fallback,
+        }
+    }
+}
+
+impl<R> Resolver for WithFallbackResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let host = host.to_owned();
+        let fallback = self.fallback.clone();
+
+        self.resolver.resolve(&host).then(move |res| {
+            match res {
+                Ok(addrs) => {
+                    if !addrs.is_empty() {
+                        return Ok(addrs);
+                    }
+                    debug!("no addresses for {}, using the fallback addresses", host);
+                }
+                Err(e) => debug!("failed to resolve {}: {}, using the fallback addresses", host, e),
+            }
+
+            Ok(fallback)
+        }).boxed()
+    }
+}
+
+#[test]
+fn test_fallback() {
+    use std::io;
+    use std::net::Ipv4Addr;
+    use futures::{failed, finished};
+
+    struct Fixed(Option<Vec<IpAddr>>);
+
+    impl Resolver for Fixed {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            match self.0 {
+                Some(ref addrs) => finished(addrs.clone()).boxed(),
+                None => failed(io::Error::new(io::ErrorKind::Other, "resolution failed")).boxed(),
+            }
+        }
+    }
+
+    let resolved = vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))];
+    let fallback = vec![IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1))];
+
+    let resolver = WithFallbackResolver::new(Fixed(Some(resolved.clone())), fallback.clone());
+    assert_eq!(resolver.resolve("example.com").wait().unwrap(), resolved);
+
+    let resolver = WithFallbackResolver::new(Fixed(Some(vec![])), fallback.clone());
+    assert_eq!(resolver.resolve("example.com").wait().unwrap(), fallback);
+
+    let resolver = WithFallbackResolver::new(Fixed(None), fallback.clone());
+    assert_eq!(resolver.resolve("example.com").wait().unwrap(), fallback);
+}
@@ -0,0 +1,112 @@
+use std::ffi::{CStr, CString};
+use std::io;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ptr;
+
+use libc;
+
+// Converts an error code of getaddrinfo into an io::Error.
+//
+// Temporary failures have the kind TimedOut, so they can be told apart
+// from a host name that doesn't exist, which has the kind NotFound, and
+// from a cancelled operation, which has the kind Interrupted.
+fn gai_error(code: libc::c_int) -> io::Error {
+    if code == libc::EAI_SYSTEM {
+        return io::Error::last_os_error();
+    }
+
+    let kind = match code {
+        libc::EAI_AGAIN => io::ErrorKind::TimedOut,
+        libc::EAI_NONAME => io::ErrorKind::NotFound,
+        _ => io::ErrorKind::Other,
+    };
+
+    let msg = unsafe { CStr::from_ptr(libc::gai_strerror(code)) };
+    io::Error::new(kind, format!("failed to lookup address information: {}", msg.to_string_lossy()))
+}
+
+/// Resolve a host name with getaddrinfo.
+///
+/// Unlike `ToSocketAddrs`, the errors tell temporary failures, with the
+/// kind `TimedOut`, from unknown host names, with the kind `NotFound`.
+/// This function blocks.
+pub fn lookup_host(host: &str) -> io::Result<Vec<IpAddr>> {
+    getaddrinfo(host, false).map(|(_, addrs)| addrs)
+}
+
+/// Resolve a host name with getaddrinfo, along with its canonical name.
+///
+/// The canonical name is the one reported in `ai_canonname`, or the host
+/// name itself if there is none. This function blocks.
+pub fn lookup_canonical(host: &str) -> io::Result<(String, Vec<IpAddr>)> {
+    getaddrinfo(host, true).map(|(canonical, addrs)| (canonical.unwrap_or_else(|| host.to_owned()), addrs))
+}
+
+fn getaddrinfo(host: &str, canonname: bool) -> io::Result<(Option<String>, Vec<IpAddr>)> {
+    let c_host = match CString::new(host) {
+        Ok(c_host) => c_host,
+        Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "host name contains a nul byte")),
+    };
+
+    let mut hints: libc::addrinfo = unsafe { mem::zeroed() };
+    if canonname {
+        hints.ai_flags = libc::AI_CANONNAME;
+    }
+    // Without a socket type, every address is listed once per socket type.
+    hints.ai_socktype = libc::SOCK_STREAM;
+
+    let mut res = ptr::null_mut();
+    let ret = unsafe { libc::getaddrinfo(c_host.as_ptr(), ptr::null(), &hints, &mut res) };
+    if ret != 0 {
+        return Err(gai_error(ret));
+    }
+
+    let mut canonical = None;
+    let mut addrs = Vec::new();
+    let mut cur = res;
+
+    while !cur.is_null() {
+        let ai = unsafe { &*cur };
+
+        if canonical.is_none() && !ai.ai_canonname.is_null() {
+            let name = unsafe { CStr::from_ptr(ai.ai_canonname) };
+            canonical = Some(name.to_string_lossy().into_owned());
+        }
+
+        match ai.ai_family {
+            libc::AF_INET => {
+                let sa = unsafe { &*(ai.ai_addr as *const libc::sockaddr_in) };
+                addrs.push(IpAddr::V4(Ipv4Addr::from(u32::from_be(sa.sin_addr.s_addr))));
+            }
+            libc::AF_INET6 => {
+                let sa = unsafe { &*(ai.ai_addr as *const libc::sockaddr_in6) };
+                addrs.push(IpAddr::V6(Ipv6Addr::from(sa.sin6_addr.s6_addr)));
+            }
+            _ => {}
+        }
+
+        cur = ai.ai_next;
+    }
+
+    unsafe { libc::freeaddrinfo(res) };
+
+    Ok((canonical, addrs))
+}
+
+#[test]
+fn test_lookup_localhost() {
+    let (name, addrs) = lookup_canonical("localhost").unwrap();
+    assert!(!name.is_empty());
+    assert!(addrs.iter().any(|addr| match *addr {
+        IpAddr::V4(ip) => ip.is_loopback(),
+        IpAddr::V6(ip) => ip.is_loopback(),
+    }));
+}
+
+#[test]
+fn test_gai_error_kinds() {
+    assert_eq!(gai_error(libc::EAI_AGAIN).kind(), io::ErrorKind::TimedOut);
+    assert_eq!(gai_error(libc::EAI_MEMORY).kind(), io::ErrorKind::Other);
+    assert_eq!(gai_error(libc::EAI_NONAME).kind(), io::ErrorKind::NotFound);
+}
@@ -0,0 +1,126 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::vec;
+
+use futures::{failed, Future, Poll};
+use tokio_core::{LoopHandle, TcpStream};
+use tokio_core::io::IoFuture;
+
+use super::common::{log_attempt, no_addresses, resolve_endpoint, sleep};
+use super::{Resolver, ToEndpoint};
+
+// Future which starts a new connection attempt every time the delay
+// elapses without a connection, or when all the running attempts failed.
+struct Hedged {
+    handle: LoopHandle,
+    delay: Duration,
+    addrs: vec::IntoIter<SocketAddr>,
+    attempts: Vec<IoFuture<TcpStream>>,
+    timer: Option<IoFuture<()>>,
+    error: Option<io::Error>,
+}
+
+impl Hedged {
+    // Starts an attempt to the next address, returns false if there are none left.
+    fn start_next(&mut self) -> bool {
+        match self.addrs.next() {
+            Some(addr) => {
+                self.attempts.push(log_attempt(addr, self.handle.clone().tcp_connect(&addr)));
+                self.timer = Some(sleep(self.handle.clone(), self.delay));
+                true
+            }
+            None => {
+                self.timer = None;
+                false
+            }
+        }
+    }
+}
+
+impl Future for Hedged {
+    type Item = TcpStream;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let mut i = 0;
+            while i < self.attempts.len() {
+                match self.attempts[i].poll() {
+                    Poll::Ok(stream) => {
+                        // The other attempts are dropped, closing their sockets.
+                        self.attempts.clear();
+                        return Poll::Ok(stream);
+                    }
+                    Poll::Err(e) => {
+                        self.attempts.remove(i);
+                        self.error = Some(e);
+                    }
+                    Poll::NotReady => i += 1,
+                }
+            }
+
+            let fired = match self.timer {
+                Some(ref mut timer) => {
+                    match timer.poll() {
+                        Poll::Ok(()) => true,
+                        Poll::Err(e) => return Poll::Err(e),
+                        Poll::NotReady => false,
+                    }
+                }
+                None => false,
+            };
+
+            if !fired && !self.attempts.is_empty() {
+                return Poll::NotReady;
+            }
+
+            if fired {
+                debug!("no connection after {:?}, hedging with another attempt", self.delay);
+            }
+
+            if !self.start_next() {
+                if self.attempts.is_empty() {
+                    return Poll::Err(self.error.take().unwrap_or_else(|| {
+                        io::Error::new(io::ErrorKind::Other, "no connection attempts were made")
+                    }));
+                }
+                return Poll::NotReady;
+            }
+        }
+    }
+}
+
+pub fn tcp_connect_hedged<'a, R, T>(handle: LoopHandle, resolver: R, ep: T, delay: Duration) -> IoFuture<TcpStream>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    let ep = match ep.to_endpoint() {
+        Ok(ep) => ep,
+        Err(e) => return failed(e).boxed(),
+    };
+
+    let resolve = resolve_endpoint(resolver, ep);
+    let ep = ep.to_buf();
+    resolve.and_then(move |addrs| {
+        if addrs.is_empty() {
+            return failed(no_addresses(&ep)).boxed();
+        }
+
+        connect_hedged(handle, addrs, delay)
+    }).boxed()
+}
+
+// Connects to one of the addresses, starting the next attempt every time the delay elapses.
+pub fn connect_hedged(handle: LoopHandle, addrs: Vec<SocketAddr>, delay: Duration) -> IoFuture<TcpStream> {
+    debug!("hedging {} connection attempts every {:?}", addrs.len(), delay);
+
+    Hedged {
+        handle: handle,
+        delay: delay,
+        addrs: addrs.into_iter(),
+        attempts: Vec::new(),
+        timer: None,
+        error: None,
+    }.boxed()
+}
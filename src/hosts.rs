@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use futures::Future;
+use futures_cpupool::CpuPool;
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+// The addresses of every name of a hosts file, by lowercase name.
+type Hosts = HashMap<String, Vec<IpAddr>>;
+
+/// A resolver based on a hosts file.
+///
+/// The file uses the same format as `/etc/hosts`. It is parsed again only
+/// when its modification time changes, so changes to the file are picked
+/// up on the next lookup. Malformed lines are skipped with a warning, once
+/// per parse.
+///
+/// Looking up a name which is not listed in the file fails, which makes
+/// this resolver suitable as the first resolver of a `ChainResolver`.
+#[derive(Clone)]
+pub struct HostsFileResolver {
+    path: Arc<PathBuf>,
+    pool: CpuPool,
+    cache: Arc<Mutex<Option<(SystemTime, Arc<Hosts>)>>>,
+}
+
+impl HostsFileResolver {
+    /// Create a new HostsFileResolver reading the file at the given path.
+    ///
+    /// The file is read inside a thread of `pool`, which can be shared
+    /// with other resolvers.
+    pub fn new<P>(path: P, pool: CpuPool) -> Self
+        where P: AsRef<Path>
+    {
+        HostsFileResolver {
+            path: Arc::new(path.as_ref().to_path_buf()),
+            pool: pool,
+            cache: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl Resolver for HostsFileResolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let path = self.path.clone();
+        let cache = self.cache.clone();
+        let host = host.to_lowercase();
+
+        self.pool.spawn_fn(move || {
+            let hosts = try!(load_hosts(&path, &cache));
+            match hosts.get(&host) {
+                Some(addrs) => Ok(addrs.clone()),
+                None => Err(io::Error::new(io::ErrorKind::NotFound, "host not found in hosts file")),
+            }
+        }).boxed()
+    }
+}
+
+// Gives the parsed file, parsing it again if it was modified since the last time.
+fn load_hosts(path: &Path, cache: &Mutex<Option<(SystemTime, Arc<Hosts>)>>) -> io::Result<Arc<Hosts>> {
+    let modified = try!(fs::metadata(path).and_then(|metadata| metadata.modified()));
+
+    let mut cache = cache.lock().unwrap();
+    if let Some((cached, ref hosts)) = *cache {
+        if cached == modified {
+            return Ok(hosts.clone());
+        }
+    }
+
+    let mut contents = String::new();
+    try!(File::open(path).and_then(|mut file| file.read_to_string(&mut contents)));
+
+    let hosts = Arc::new(parse_hosts(&contents));
+    *cache = Some((modified, hosts.clone()));
+    Ok(hosts)
+}
+
+// Find the addresses of every name in the contents of a hosts file.
+fn parse_hosts(contents: &str) -> Hosts {
+    let mut hosts = Hosts::new();
+
+    for (num, line) in contents.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(idx) => &line[..idx],
+            None => line,
+        };
+
+        let mut fields = line.split_whitespace();
+        let addr = match fields.next() {
+            Some(addr) => addr,
+            None => continue,
+        };
+
+        let addr = match IpAddr::from_str(addr) {
+            Ok(addr) => addr,
+            Err(_) => {
+                warn!("skipping malformed hosts file line {}: invalid address {:?}", num + 1, addr);
+                continue;
+            }
+        };
+
+        for name in fields {
+            hosts.entry(name.to_lowercase()).or_insert_with(Vec::new).push(addr);
+        }
+    }
+
+    hosts
+}
+
+#[test]
+fn test_parse_hosts() {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let contents = "# comment\n\
+                    127.0.0.1 localhost example\n\
+                    not-an-ip example\n\
+                    \n\
+                    10.0.0.1\tother # trailing comment\n\
+                    ::1 LocalHost\n";
+
+    let hosts = parse_hosts(contents);
+    assert_eq!(hosts["localhost"],
+               vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+                    IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1))]);
+    assert_eq!(hosts["example"], vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))]);
+    assert_eq!(hosts["other"], vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+    assert!(!hosts.contains_key("comment"));
+}
+
+#[test]
+fn test_hosts_file_resolver() {
+    use std::env;
+    use std::io::Write;
+    use std::net::Ipv4Addr;
+
+    let path = env::temp_dir().join("tokio-dns-test-hosts");
+    File::create(&path).and_then(|mut file| file.write_all(b"127.0.0.1 localhost\n")).unwrap();
+
+    let resolver = HostsFileResolver::new(&path, CpuPool::new(1));
+    for _ in 0..2 {
+        assert_eq!(resolver.resolve("LOCALHOST").wait().unwrap(), vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))]);
+    }
+    assert!(resolver.cache.lock().unwrap().is_some());
+    assert_eq!(resolver.resolve("other").wait().unwrap_err().kind(), io::ErrorKind::NotFound);
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,176 @@
+use std::io::{self, Read};
+use std::mem;
+use std::str;
+
+use futures::{failed, Future, Poll};
+use tokio_core::TcpStream;
+use tokio_core::io::{flush, write_all, IoFuture};
+
+use super::ToEndpoint;
+
+// The response head of the proxy is not allowed to be longer than this.
+const MAX_RESPONSE_HEAD: usize = 8192;
+
+// The end of the response head.
+const END_OF_HEAD: &'static [u8] = b"\r\n\r\n";
+
+// Future which reads the response head of the proxy, and nothing after it.
+struct ReadResponseHead<S> {
+    stream: Option<S>,
+    head: Vec<u8>,
+}
+
+impl<S> Future for ReadResponseHead<S> where S: Read {
+    type Item = (S, Vec<u8>);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if self.head.len() >= MAX_RESPONSE_HEAD {
+                return Poll::Err(io::Error::new(io::ErrorKind::InvalidData, "the response of the proxy is too long"));
+            }
+
+            // Never more than what's left of the end of the head, so the
+            // data of the tunnel that follows is left in the stream.
+            let mut buf = [0u8; 4];
+            let len = END_OF_HEAD.len() - partial_end(&self.head);
+            let res = self.stream.as_mut().expect("polled ReadResponseHead after completion").read(&mut buf[..len]);
+            let n = match res {
+                Ok(0) => {
+                    return Poll::Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                    "the proxy closed the connection before responding"));
+                }
+                Ok(n) => n,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Poll::NotReady,
+                Err(e) => return Poll::Err(e),
+            };
+
+            self.head.extend_from_slice(&buf[..n]);
+            if self.head.ends_with(END_OF_HEAD) {
+                let stream = self.stream.take().unwrap();
+                return Poll::Ok((stream, mem::replace(&mut self.head, Vec::new())));
+            }
+        }
+    }
+}
+
+// The number of bytes of the end of the head that `head` already ends with.
+fn partial_end(head: &[u8]) -> usize {
+    (1..END_OF_HEAD.len()).rev().find(|&n| head.ends_with(&END_OF_HEAD[..n])).unwrap_or(0)
+}
+
+/// Open a tunnel through an HTTP proxy on a stream connected to it.
+///
+/// A `CONNECT` request for `target`, formatted as `<host>:<port>`, is sent
+/// to the proxy. If `authorization` is given, it is sent as the value of
+/// the `Proxy-Authorization` header, such as `Basic <credentials>`. The
+/// future resolves into the stream once the proxy accepted the tunnel with
+/// a `2xx` response. Other responses are errors, with the status line of
+/// the proxy in their message.
+///
+/// The response is read a few bytes at a time, never beyond its end, so none
+/// of the data sent through the tunnel by the target is consumed.
+pub fn http_connect(stream: TcpStream, target: &str, authorization: Option<&str>) -> IoFuture<TcpStream> {
+    let request = connect_request(target, authorization);
+
+    write_all(stream, request).and_then(|(stream, _)| flush(stream)).and_then(|stream| {
+        ReadResponseHead {
+            stream: Some(stream),
+            head: Vec::new(),
+        }
+    }).and_then(|(stream, head)| {
+        try!(check_response_head(&head));
+        Ok(stream)
+    }).boxed()
+}
+
+// Chains the tunnel request to the connection to the proxy.
+pub fn via_http_proxy<'a, T>(connect: IoFuture<TcpStream>, target: T, authorization: Option<&str>)
+                             -> IoFuture<TcpStream>
+    where T: ToEndpoint<'a>
+{
+    let target = match target.to_endpoint() {
+        Ok(target) => target.to_string(),
+        Err(e) => return failed(e).boxed(),
+    };
+    let authorization = authorization.map(|auth| auth.to_owned());
+
+    connect.and_then(move |stream| {
+        debug!("opening a tunnel to {} through the HTTP proxy", target);
+        http_connect(stream, &target, authorization.as_ref().map(|auth| &auth[..]))
+    }).boxed()
+}
+
+fn connect_request(target: &str, authorization: Option<&str>) -> Vec<u8> {
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", target, target);
+    if let Some(authorization) = authorization {
+        request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+    }
+    request.push_str("\r\n");
+    request.into_bytes()
+}
+
+fn check_response_head(head: &[u8]) -> io::Result<()> {
+    let line = head.split(|&b| b == b'\n').next().unwrap_or(head);
+    let line = match str::from_utf8(line) {
+        Ok(line) => line.trim_right_matches('\r'),
+        Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed response from the proxy")),
+    };
+
+    let mut parts = line.split(' ');
+    match (parts.next(), parts.next()) {
+        (Some(version), Some(status)) if version.starts_with("HTTP/") => {
+            // Any successful response establishes the tunnel (RFC 7231, section 4.3.6).
+            if status.len() == 3 && status.starts_with('2') && status.parse::<u16>().is_ok() {
+                Ok(())
+            } else {
+                Err(io::Error::new(io::ErrorKind::Other, format!("the proxy refused the tunnel: {}", line)))
+            }
+        }
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("malformed response from the proxy: {}", line))),
+    }
+}
+
+#[test]
+fn test_connect_request() {
+    assert_eq!(connect_request("example.com:443", None),
+               b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n".to_vec());
+    assert_eq!(connect_request("example.com:443", Some("Basic dXNlcjpwYXNz")),
+               b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\
+                 Proxy-Authorization: Basic dXNlcjpwYXNz\r\n\r\n".to_vec());
+}
+
+#[test]
+fn test_check_response_head() {
+    assert!(check_response_head(b"HTTP/1.1 200 Connection established\r\n\r\n").is_ok());
+    assert!(check_response_head(b"HTTP/1.0 200 OK\r\nProxy-Agent: test\r\n\r\n").is_ok());
+    assert!(check_response_head(b"HTTP/1.1 204 No Content\r\n\r\n").is_ok());
+
+    let e = check_response_head(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n").unwrap_err();
+    assert_eq!(e.to_string(), "the proxy refused the tunnel: HTTP/1.1 407 Proxy Authentication Required");
+
+    assert!(check_response_head(b"HTTP/1.1 2000 Weird\r\n\r\n").is_err());
+
+    let e = check_response_head(b"SSH-2.0-OpenSSH\r\n\r\n").unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_read_response_head() {
+    use std::io::Cursor;
+
+    let response = b"HTTP/1.1 200 OK\r\nProxy-Agent: test\r\n\r\nSSH-2.0-OpenSSH\r\n".to_vec();
+    let read = ReadResponseHead {
+        stream: Some(Cursor::new(response)),
+        head: Vec::new(),
+    };
+    let (stream, head) = read.wait().unwrap();
+    assert_eq!(head, b"HTTP/1.1 200 OK\r\nProxy-Agent: test\r\n\r\n".to_vec());
+    assert_eq!(stream.position(), head.len() as u64);
+
+    let read = ReadResponseHead {
+        stream: Some(Cursor::new(b"HTTP/1.1 200 OK\r\n".to_vec())),
+        head: Vec::new(),
+    };
+    assert_eq!(read.wait().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,145 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::time::Duration;
+
+// The weight of a new measurement in the moving average.
+const WEIGHT: f64 = 0.3;
+
+struct Entry {
+    // The moving average of the connect times, in seconds.
+    average: f64,
+    // The tick of the last use of the entry, the oldest one is evicted first.
+    used: usize,
+}
+
+fn secs(dur: Duration) -> f64 {
+    dur.as_secs() as f64 + dur.subsec_nanos() as f64 / 1e9
+}
+
+/// Connect times of addresses.
+///
+/// The time taken to establish a connection is recorded for every address,
+/// as a moving average where older measurements lose weight. At most
+/// `capacity` addresses are remembered, the least recently used one is
+/// forgotten first.
+///
+/// The latencies can be cloned cheaply, the clones share the same data.
+/// Give them to a `Connector` with `Connector::latencies` to try the
+/// fastest addresses first in `tcp_connect_seq`.
+#[derive(Clone)]
+pub struct ConnectLatencies {
+    entries: Arc<Mutex<HashMap<IpAddr, Entry>>>,
+    capacity: usize,
+    clock: Arc<AtomicUsize>,
+}
+
+impl ConnectLatencies {
+    /// Create new, empty latencies of at most `capacity` addresses.
+    pub fn new(capacity: usize) -> Self {
+        ConnectLatencies {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            capacity: capacity,
+            clock: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Record the time it took to connect to `addr`.
+    pub fn record(&self, addr: IpAddr, time: Duration) {
+        let time = secs(time);
+        let used = self.clock.fetch_add(1, AtomicOrdering::SeqCst);
+        let mut entries = self.entries.lock().unwrap();
+
+        {
+            let entry = entries.entry(addr).or_insert(Entry {
+                average: time,
+                used: used,
+            });
+
+            entry.average += (time - entry.average) * WEIGHT;
+            entry.used = used;
+        }
+
+        while entries.len() > self.capacity {
+            let oldest = match entries.iter().min_by_key(|&(_, entry)| entry.used) {
+                Some((&oldest, _)) => oldest,
+                None => break,
+            };
+            entries.remove(&oldest);
+        }
+    }
+
+    /// Get the average time it took to connect to `addr`, if it is known.
+    pub fn get(&self, addr: IpAddr) -> Option<Duration> {
+        let entries = self.entries.lock().unwrap();
+
+        entries.get(&addr).map(|entry| {
+            let nanos = (entry.average * 1e9) as u64;
+            Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+        })
+    }
+
+    /// Sort the addresses from the fastest to the slowest.
+    ///
+    /// An address without a known latency is considered as fast as the
+    /// average of the known latencies of the addresses, so it is tried
+    /// after the fast addresses but before the slow ones. Addresses with
+    /// the same latency keep their order.
+    pub fn sort(&self, addrs: &mut Vec<IpAddr>) {
+        let known: Vec<Option<f64>> = {
+            let entries = self.entries.lock().unwrap();
+            addrs.iter().map(|addr| entries.get(addr).map(|entry| entry.average)).collect()
+        };
+
+        let (sum, count) = known.iter().fold((0.0, 0), |(sum, count), latency| match *latency {
+            Some(latency) => (sum + latency, count + 1),
+            None => (sum, count),
+        });
+        if count == 0 {
+            return;
+        }
+        let average = sum / count as f64;
+
+        let mut scored: Vec<(f64, IpAddr)> = known.into_iter()
+                                                  .zip(addrs.iter())
+                                                  .map(|(latency, &addr)| (latency.unwrap_or(average), addr))
+                                                  .collect();
+        scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        addrs.clear();
+        addrs.extend(scored.into_iter().map(|(_, addr)| addr));
+    }
+}
+
+#[test]
+fn test_sort() {
+    use std::net::Ipv4Addr;
+
+    let fast = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let slow = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    let unknown = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+
+    let latencies = ConnectLatencies::new(10);
+    latencies.record(fast, Duration::from_millis(10));
+    latencies.record(slow, Duration::from_millis(200));
+
+    let mut addrs = vec![slow, unknown, fast];
+    latencies.sort(&mut addrs);
+    assert_eq!(addrs, vec![fast, unknown, slow]);
+}
+
+#[test]
+fn test_capacity() {
+    use std::net::Ipv4Addr;
+
+    let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    let latencies = ConnectLatencies::new(1);
+    latencies.record(a, Duration::from_millis(10));
+    latencies.record(b, Duration::from_millis(10));
+    assert!(latencies.get(a).is_none());
+    assert!(latencies.get(b).is_some());
+}
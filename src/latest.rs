@@ -0,0 +1,104 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+
+use futures::{Future, Poll};
+use futures::stream::Stream;
+use tokio_core::{LoopHandle, TcpStream};
+use tokio_core::io::{IoFuture, IoStream};
+
+use super::strategy::{connect_to_addrs, Strategy};
+
+// Stream which connects to the latest set of addresses received.
+struct ConnectLatest<S> {
+    handle: LoopHandle,
+    sets: S,
+    port: u16,
+    strategy: Strategy,
+    done: bool,
+    current: Option<IoFuture<TcpStream>>,
+}
+
+impl<S> Stream for ConnectLatest<S> where S: Stream<Item = Vec<IpAddr>, Error = io::Error> {
+    type Item = TcpStream;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut latest = None;
+
+        // Only the most recent of the sets waiting is connected to.
+        while !self.done {
+            match self.sets.poll() {
+                Poll::Ok(Some(ip_addrs)) => latest = Some(ip_addrs),
+                Poll::Ok(None) => self.done = true,
+                Poll::Err(e) => return Poll::Err(e),
+                Poll::NotReady => break,
+            }
+        }
+
+        if let Some(ip_addrs) = latest {
+            if self.current.is_some() {
+                debug!("new addresses received, abandoning the current connection attempts");
+            }
+
+            let port = self.port;
+            let addrs = ip_addrs.into_iter().map(|ip_addr| SocketAddr::new(ip_addr, port)).collect();
+            self.current = Some(connect_to_addrs(self.handle.clone(), addrs, self.strategy, None));
+        }
+
+        let res = match self.current {
+            Some(ref mut fut) => {
+                match fut.poll() {
+                    Poll::Ok(stream) => Ok(stream),
+                    Poll::Err(e) => Err(e),
+                    Poll::NotReady => return Poll::NotReady,
+                }
+            }
+            None if self.done => return Poll::Ok(None),
+            None => return Poll::NotReady,
+        };
+
+        self.current = None;
+        match res {
+            Ok(stream) => Poll::Ok(Some(stream)),
+            Err(e) => Poll::Err(e),
+        }
+    }
+}
+
+/// Connect to the latest set of addresses yielded by a stream.
+///
+/// Every time the stream yields a set of addresses, a connection to one of
+/// them on `port` is attempted according to `strategy`. The connection is
+/// yielded once established, or the error if every attempt failed. When
+/// a new set arrives before the attempts complete, they are abandoned in
+/// favor of the new set. The stream ends with the stream of sets, once
+/// the last attempts complete.
+///
+/// The sets can come from a channel fed by service discovery, or from
+/// `DnsSupport::watch_resolution`, so that a connection is established
+/// again whenever the addresses change.
+pub fn tcp_connect_latest<S>(handle: LoopHandle, sets: S, port: u16, strategy: Strategy) -> IoStream<TcpStream>
+    where S: Stream<Item = Vec<IpAddr>, Error = io::Error> + Send + 'static
+{
+    ConnectLatest {
+        handle: handle,
+        sets: sets,
+        port: port,
+        strategy: strategy,
+        done: false,
+        current: None,
+    }.boxed()
+}
+
+#[test]
+fn test_empty_set() {
+    use futures::stream;
+    use tokio_core::Loop;
+
+    let lp = Loop::new().unwrap();
+    let sets = stream::iter(vec![Ok(vec![])]);
+    match tcp_connect_latest(lp.handle(), sets, 80, Strategy::Sequential).collect().wait() {
+        Ok(_) => panic!("connected without addresses"),
+        Err(e) => assert_eq!(e.kind(), io::ErrorKind::Other),
+    }
+}
@@ -7,9 +7,11 @@
 //! whether the given endpoint is a socket address or a host name. Then, it
 //! is up to a resolver to perform name resolution.
 //!
-//! The `Resolver` trait describes an abstract, asynchronous resolver. This crate
-//! provides one (for now) implementation of a resolver, the `CpuPoolResolver`.
-//! It uses a thread pool and the `ToSocketAddrs` trait to perform name resolution.
+//! The `Resolver` trait describes an abstract, asynchronous resolver. The main
+//! implementation of a resolver is the `CpuPoolResolver`. It uses a thread pool
+//! and the `ToSocketAddrs` trait to perform name resolution. The `HostsFileResolver`
+//! reads addresses from a hosts file, and the `ChainResolver` combines two
+//! resolvers by falling back to the second one when the first one fails.
 //!
 //! The `DnsSupport` trait is an extension trait that adds name resolution to an
 //! object. There's an implementation for `LoopHandle` that uses a `lazy_static!`
@@ -21,11 +23,21 @@
 //! you give it when resolving. Refer to the `examples/connector.rs` file to
 //! see an example of it.
 //!
+//! The helpers that work over time, like `DnsSupport::watch_resolution` or
+//! the timers of the connect strategies, take a `LoopHandle` and return a
+//! future or a stream. Nothing is spawned in the background: the work is
+//! driven by polling what was returned, and it stops as soon as that is
+//! dropped. To keep such work running on its own, spawn it on the event
+//! loop. A spawned future can still be stopped with `cancelable` or
+//! `abortable`.
+//!
 //! [Git Repository](https://github.com/sbstp/tokio-dns)
 #![deny(missing_docs)]
 
 extern crate futures;
 extern crate futures_cpupool;
+extern crate libc;
+extern crate rand;
 extern crate tokio_core;
 
 #[macro_use]
@@ -33,22 +45,122 @@ extern crate lazy_static;
 #[macro_use]
 extern crate log;
 
+mod addresses;
+#[cfg(unix)]
+mod alive;
+mod audit;
+mod budget;
+mod buffered;
+mod cancel;
+mod candidates;
+mod chain;
 mod common;
 mod connector;
+#[cfg(unix)]
+mod dscp;
 mod endpoint;
+mod env;
+mod fallback;
+#[cfg(unix)]
+mod getaddrinfo;
+mod hedged;
+mod hosts;
+mod http_proxy;
+mod latency;
+mod latest;
+#[cfg(target_os = "linux")]
+mod linux;
+mod many;
+mod map;
+mod mapped;
+mod mdns;
+mod options;
+mod proxy_protocol;
+mod race;
+mod resolve_fn;
+mod rewrite;
+mod routing;
 mod select_all_ok;
+mod select_n_ok;
+mod serve;
+mod shuffle;
+mod slow;
+mod socks4;
+mod stats;
+mod sticky;
+mod strategy;
 mod support;
+mod sync;
+#[cfg(unix)]
+mod timeouts;
+mod timings;
+mod transport;
+mod truncate;
+mod udp;
+mod union;
+mod watch;
 
 use std::io;
-use std::net::{IpAddr, ToSocketAddrs};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use futures::{BoxFuture, Future};
+use futures::{failed, BoxFuture, Future};
 use futures_cpupool::CpuPool;
-use tokio_core::io::IoFuture;
+use tokio_core::TcpStream;
+use tokio_core::io::{IoFuture, IoStream};
 
+#[cfg(unix)]
+pub use alive::is_connection_alive;
+pub use audit::{AuditRecord, AuditResolver};
+pub use budget::BudgetResolver;
+pub use buffered::BufStream;
+pub use cancel::{abortable, cancel_token, cancelable, AbortHandle, CancelToken, Canceler};
+pub use chain::ChainResolver;
 pub use connector::Connector;
-pub use endpoint::{Endpoint, ToEndpoint};
+#[cfg(unix)]
+pub use dscp::set_dscp;
+pub use endpoint::{allow_ports, AllowPorts, Endpoint, EndpointBuf, ToEndpoint};
+pub use env::EnvResolver;
+pub use fallback::WithFallbackResolver;
+pub use hosts::HostsFileResolver;
+pub use http_proxy::http_connect;
+pub use latency::ConnectLatencies;
+pub use latest::tcp_connect_latest;
+#[cfg(target_os = "linux")]
+pub use linux::{set_quickack, set_user_timeout};
+pub use map::MapResolver;
+pub use mapped::Ipv4Mapping;
+pub use mdns::MdnsResolver;
+pub use options::SocketOptions;
+pub use proxy_protocol::{write_proxy_header, ProxyHeader};
+pub use race::RaceResolver;
+pub use resolve_fn::tcp_connect_with;
+pub use rewrite::EndpointRewriter;
+pub use routing::RoutingResolver;
+pub use shuffle::ShuffleResolver;
+pub use slow::SlowQueryLogResolver;
+pub use socks4::socks4_connect;
+pub use stats::ConnectStats;
+pub use sticky::StickyAddresses;
+pub use strategy::{connect_to_addrs, Strategy};
 pub use support::DnsSupport;
+pub use sync::SyncResolver;
+#[cfg(unix)]
+pub use timeouts::set_socket_timeouts;
+pub use timings::ConnectTimings;
+pub use transport::{connect_par, connect_seq, Transport};
+pub use truncate::TruncateResolver;
+pub use udp::udp_recv_timeout;
+pub use union::UnionResolver;
+
+/// The future of a connection, as returned by the connect helpers.
+///
+/// This is the `IoFuture<TcpStream>` returned by the methods of `DnsSupport`
+/// and `Connector`, under a name. It is `Send + 'static`, so it can be stored
+/// in a struct field, sent to another thread or returned from a combinator
+/// built on top of this crate without boxing it again.
+pub type ConnectFuture = IoFuture<TcpStream>;
 
 /// The Resolver trait represents an object capable of
 /// resolving host names into IP addresses.
@@ -56,15 +168,62 @@ pub trait Resolver {
     /// Given a host name, this function returns a Future which
     /// will eventually resolve into a list of IP addresses.
     fn resolve(&self, host: &str) -> BoxFuture<Vec<IpAddr>, io::Error>;
+
+    /// Given a host name, this function returns a Stream which
+    /// yields its IP addresses as soon as they are known.
+    ///
+    /// By default, the addresses are yielded once `resolve` completes.
+    /// Resolvers which learn the addresses incrementally can yield the
+    /// first ones earlier, so connection attempts start sooner.
+    fn resolve_stream(&self, host: &str) -> IoStream<IpAddr> {
+        addresses::addresses(self.resolve(host))
+    }
+
+    /// Given a host name, this function returns a Future which will
+    /// eventually resolve into its canonical name and its IP addresses.
+    ///
+    /// The canonical name is the name the host name is an alias of, through
+    /// CNAME records for instance. By default, the host name itself is
+    /// returned as the canonical name.
+    fn resolve_canonical(&self, host: &str) -> IoFuture<(String, Vec<IpAddr>)> {
+        let name = host.to_owned();
+        self.resolve(host).map(move |addrs| (name, addrs)).boxed()
+    }
+
+    /// Given a host name, this function returns a Future which will
+    /// eventually resolve into its IPv4 and its IPv6 addresses.
+    ///
+    /// The addresses of each family are in the order they were resolved in.
+    fn resolve_grouped(&self, host: &str) -> IoFuture<(Vec<Ipv4Addr>, Vec<Ipv6Addr>)> {
+        self.resolve(host).map(|addrs| {
+            let mut v4 = Vec::new();
+            let mut v6 = Vec::new();
+            for addr in addrs {
+                match addr {
+                    IpAddr::V4(addr) => v4.push(addr),
+                    IpAddr::V6(addr) => v6.push(addr),
+                }
+            }
+            (v4, v6)
+        }).boxed()
+    }
 }
 
 /// A resolver based on a thread pool.
 ///
 /// This resolver uses the `ToSocketAddrs` trait inside
 /// a thread to provide non-blocking address resolving.
+///
+/// On Unix platforms, `getaddrinfo` is called directly instead. Temporary
+/// failures of the resolution then fail with an error of kind
+/// `TimedOut`, and unknown host names with an error of kind `NotFound`,
+/// so that retry logic can give up on the latter.
 #[derive(Clone)]
 pub struct CpuPoolResolver {
     pool: CpuPool,
+    num_threads: usize,
+    pending: Arc<AtomicUsize>,
+    max_queued: Option<usize>,
 }
 
 impl CpuPoolResolver {
@@ -72,18 +231,121 @@ impl CpuPoolResolver {
     pub fn new(num_threads: usize) -> Self {
         CpuPoolResolver {
             pool: CpuPool::new(num_threads),
+            num_threads: num_threads,
+            pending: Arc::new(AtomicUsize::new(0)),
+            max_queued: None,
+        }
+    }
+
+    /// Set the number of resolutions that may wait for a thread before `try_resolve` refuses new ones.
+    ///
+    /// Without a limit, `try_resolve` behaves like `resolve`.
+    pub fn max_queued(mut self, max: usize) -> Self {
+        self.max_queued = Some(max);
+        self
+    }
+
+    /// Get the number of resolutions waiting for a free thread.
+    pub fn queue_depth(&self) -> usize {
+        self.pending.load(Ordering::SeqCst).saturating_sub(self.num_threads)
+    }
+
+    /// Resolve the host name, unless the pool is saturated.
+    ///
+    /// If every thread is busy and `max_queued` resolutions are already
+    /// waiting, this fails immediately with an error of kind `WouldBlock`
+    /// instead of queueing the resolution, so the caller can shed load.
+    pub fn try_resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        if let Some(max) = self.max_queued {
+            if self.pending.load(Ordering::SeqCst) >= self.num_threads + max {
+                debug!("refusing to resolve {}, {} resolutions are queued", host, self.queue_depth());
+                return failed(io::Error::new(io::ErrorKind::WouldBlock, "the resolver pool is saturated")).boxed();
+            }
         }
+
+        self.resolve(host)
+    }
+}
+
+// On Unix, getaddrinfo is called directly so that temporary failures
+// can be told apart from unknown host names.
+#[cfg(unix)]
+fn lookup_host(host: &str) -> io::Result<Vec<IpAddr>> {
+    getaddrinfo::lookup_host(host)
+}
+
+#[cfg(not(unix))]
+fn lookup_host(host: &str) -> io::Result<Vec<IpAddr>> {
+    use std::net::ToSocketAddrs;
+
+    match format!("{}:0", host)[..].to_socket_addrs() {
+        Ok(it) => Ok(it.map(|s| s.ip()).collect()),
+        Err(e) => Err(e),
+    }
+}
+
+// Counts a resolution as pending until it is dropped.
+struct Pending(Arc<AtomicUsize>);
+
+impl Pending {
+    fn new(pending: &Arc<AtomicUsize>) -> Self {
+        pending.fetch_add(1, Ordering::SeqCst);
+        Pending(pending.clone())
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
     }
 }
 
 impl Resolver for CpuPoolResolver {
     fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
-        let host = format!("{}:0", host);
+        let host = host.to_owned();
+        let pending = Pending::new(&self.pending);
+
         self.pool.spawn_fn(move || {
-            match host[..].to_socket_addrs() {
-                Ok(it) => Ok(it.map(|s| s.ip()).collect()),
-                Err(e) => Err(e),
-            }
+            let _pending = pending;
+            lookup_host(&host)
         }).boxed()
     }
+
+    /// The canonical name is requested from `getaddrinfo` with `AI_CANONNAME`.
+    #[cfg(unix)]
+    fn resolve_canonical(&self, host: &str) -> IoFuture<(String, Vec<IpAddr>)> {
+        let host = host.to_owned();
+        let pending = Pending::new(&self.pending);
+
+        self.pool.spawn_fn(move || {
+            let _pending = pending;
+            getaddrinfo::lookup_canonical(&host)
+        }).boxed()
+    }
+}
+
+#[test]
+fn test_resolve_grouped() {
+    use futures::finished;
+
+    struct Mixed;
+
+    impl Resolver for Mixed {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished(vec![IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)),
+                          IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                          IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))]).boxed()
+        }
+    }
+
+    let (v4, v6) = Mixed.resolve_grouped("example.com").wait().unwrap();
+    assert_eq!(v4, vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)]);
+    assert_eq!(v6, vec![Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)]);
+}
+
+#[test]
+fn test_connect_future_bounds() {
+    fn assert_send_static<T: Send + 'static>() {}
+
+    assert_send_static::<ConnectFuture>();
 }
@@ -0,0 +1,55 @@
+use std::io;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+use libc;
+use tokio_core::TcpStream;
+
+/// Enable or disable the quick acknowledgement mode of a connection.
+///
+/// In quick acknowledgement mode, `TCP_QUICKACK`, segments are acknowledged
+/// immediately instead of being delayed. It complements `TCP_NODELAY`:
+/// Nagle's algorithm holds small writes back until the previous ones are
+/// acknowledged, so delayed acknowledgements on the other side add latency
+/// to a request-response exchange. The kernel leaves this mode on its own
+/// as the connection goes on, so it isn't a permanent setting.
+///
+/// This function is only available on Linux.
+pub fn set_quickack(stream: &TcpStream, enabled: bool) -> io::Result<()> {
+    set_tcp_option(stream, libc::TCP_QUICKACK, if enabled { 1 } else { 0 })
+}
+
+/// Set the user timeout of a connection.
+///
+/// The user timeout, `TCP_USER_TIMEOUT`, is how long the data sent may
+/// remain unacknowledged before the connection is dropped. It detects a
+/// dead peer much sooner than the retransmissions of the system, which
+/// take about 15 minutes. The timeout is rounded down to milliseconds,
+/// and zero restores the system default.
+///
+/// This function is only available on Linux.
+pub fn set_user_timeout(stream: &TcpStream, timeout: Duration) -> io::Result<()> {
+    let millis = timeout.as_secs().saturating_mul(1000).saturating_add((timeout.subsec_nanos() / 1_000_000) as u64);
+    if millis > libc::c_int::max_value() as u64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the user timeout is too long"));
+    }
+
+    set_tcp_option(stream, libc::TCP_USER_TIMEOUT, millis as libc::c_int)
+}
+
+fn set_tcp_option(stream: &TcpStream, opt: libc::c_int, value: libc::c_int) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(stream.as_raw_fd(),
+                         libc::IPPROTO_TCP,
+                         opt,
+                         &value as *const libc::c_int as *const libc::c_void,
+                         mem::size_of::<libc::c_int>() as libc::socklen_t)
+    };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
@@ -0,0 +1,112 @@
+use std::io;
+use std::mem;
+use std::vec;
+
+use futures::{Future, Poll};
+use tokio_core::TcpStream;
+use tokio_core::io::IoFuture;
+
+use super::{EndpointBuf, ToEndpoint};
+
+// Future which connects to every endpoint, with at most `limit` of them at a time.
+struct ConnectMany<F> {
+    connect: F,
+    limit: usize,
+    pending: vec::IntoIter<(usize, EndpointBuf)>,
+    running: Vec<(usize, IoFuture<TcpStream>)>,
+    results: Vec<Option<io::Result<TcpStream>>>,
+}
+
+impl<F> Future for ConnectMany<F> where F: Fn(EndpointBuf) -> IoFuture<TcpStream> {
+    type Item = Vec<io::Result<TcpStream>>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            while self.running.len() < self.limit {
+                match self.pending.next() {
+                    Some((idx, ep)) => self.running.push((idx, (self.connect)(ep))),
+                    None => break,
+                }
+            }
+
+            let mut completed = false;
+            let mut i = 0;
+            while i < self.running.len() {
+                let res = match self.running[i].1.poll() {
+                    Poll::Ok(stream) => Ok(stream),
+                    Poll::Err(e) => Err(e),
+                    Poll::NotReady => {
+                        i += 1;
+                        continue;
+                    }
+                };
+
+                let (idx, _) = self.running.remove(i);
+                self.results[idx] = Some(res);
+                completed = true;
+            }
+
+            if self.running.is_empty() && self.pending.len() == 0 {
+                let results = mem::replace(&mut self.results, Vec::new());
+                return Poll::Ok(results.into_iter().map(|res| res.unwrap()).collect());
+            }
+
+            if !completed {
+                return Poll::NotReady;
+            }
+        }
+    }
+}
+
+// Connects to every endpoint with `connect`, at most `limit` at a time, and returns a result per endpoint.
+pub fn tcp_connect_many<'a, I, T, F>(eps: I, limit: usize, connect: F) -> IoFuture<Vec<io::Result<TcpStream>>>
+    where I: IntoIterator<Item = T>,
+          T: ToEndpoint<'a>,
+          F: Fn(EndpointBuf) -> IoFuture<TcpStream> + Send + 'static
+{
+    let mut pending = Vec::new();
+    let mut results = Vec::new();
+
+    for (idx, ep) in eps.into_iter().enumerate() {
+        match ep.to_endpoint() {
+            Ok(ep) => {
+                pending.push((idx, ep.to_buf()));
+                results.push(None);
+            }
+            Err(e) => results.push(Some(Err(e))),
+        }
+    }
+
+    debug!("connecting to {} endpoints, {} at a time", pending.len(), limit);
+
+    ConnectMany {
+        connect: connect,
+        limit: if limit == 0 { 1 } else { limit },
+        pending: pending.into_iter(),
+        running: Vec::new(),
+        results: results,
+    }.boxed()
+}
+
+#[test]
+fn test_results_in_order() {
+    use std::sync::{Arc, Mutex};
+    use futures::failed;
+
+    let attempts = Arc::new(Mutex::new(Vec::new()));
+    let recorded = attempts.clone();
+    let connect = move |ep: EndpointBuf| {
+        recorded.lock().unwrap().push(ep.port());
+        failed(io::Error::new(io::ErrorKind::ConnectionRefused, "refused")).boxed()
+    };
+
+    let eps = vec!["a.example.com:1", "invalid", "b.example.com:2"];
+    let results = tcp_connect_many(eps, 1, connect).wait().unwrap();
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().err().unwrap().kind(), io::ErrorKind::ConnectionRefused);
+    assert_eq!(results[1].as_ref().err().unwrap().kind(), io::ErrorKind::Other);
+    assert_eq!(results[2].as_ref().err().unwrap().kind(), io::ErrorKind::ConnectionRefused);
+    assert_eq!(*attempts.lock().unwrap(), vec![1, 2]);
+}
@@ -0,0 +1,74 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use futures::Future;
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A resolver that rewrites the addresses returned by another resolver.
+///
+/// The addresses returned by the inner resolver are given to the function,
+/// and the addresses it returns are the result of the resolution. This is
+/// the most general way of post-processing a resolution, for instance to
+/// translate public addresses into internal ones.
+pub struct MapResolver<R, F> {
+    resolver: R,
+    func: Arc<F>,
+}
+
+impl<R, F> MapResolver<R, F>
+    where R: Resolver,
+          F: Fn(Vec<IpAddr>) -> Vec<IpAddr> + Send + Sync + 'static
+{
+    /// Create a new MapResolver applying `func` to the addresses.
+    pub fn new(resolver: R, func: F) -> Self {
+        MapResolver {
+            resolver: resolver,
+            func: Arc::new(func),
+        }
+    }
+}
+
+impl<R, F> Clone for MapResolver<R, F> where R: Clone {
+    fn clone(&self) -> Self {
+        MapResolver {
+            resolver: self.resolver.clone(),
+            func: self.func.clone(),
+        }
+    }
+}
+
+impl<R, F> Resolver for MapResolver<R, F>
+    where R: Resolver,
+          F: Fn(Vec<IpAddr>) -> Vec<IpAddr> + Send + Sync + 'static
+{
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let func = self.func.clone();
+        self.resolver.resolve(host).map(move |addrs| func(addrs)).boxed()
+    }
+}
+
+#[test]
+fn test_map() {
+    use std::net::Ipv4Addr;
+    use futures::finished;
+
+    struct Fixed;
+
+    impl Resolver for Fixed {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished(vec![IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)), IpAddr::V4(Ipv4Addr::new(203, 0, 113, 2))]).boxed()
+        }
+    }
+
+    let resolver = MapResolver::new(Fixed, |addrs: Vec<IpAddr>| {
+        addrs.into_iter().map(|addr| match addr {
+            IpAddr::V4(v4) if v4 == Ipv4Addr::new(203, 0, 113, 1) => IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            addr => addr,
+        }).collect()
+    });
+
+    assert_eq!(resolver.resolve("example.com").wait().unwrap(),
+               vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), IpAddr::V4(Ipv4Addr::new(203, 0, 113, 2))]);
+}
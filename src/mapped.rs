@@ -0,0 +1,67 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// The representation of IPv4 addresses.
+///
+/// On dual-stack systems, an IPv4 address can also be written as an
+/// IPv4-mapped IPv6 address, such as `::ffff:192.0.2.1`. A mapping
+/// turns the addresses into one representation, so the two forms of
+/// the same address are treated as the same address.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Ipv4Mapping {
+    /// IPv4-mapped IPv6 addresses are turned into IPv4 addresses.
+    Unmapped,
+    /// IPv4 addresses are turned into IPv4-mapped IPv6 addresses.
+    Mapped,
+}
+
+impl Ipv4Mapping {
+    /// Represent the given address according to this mapping.
+    ///
+    /// Addresses that are neither IPv4 nor IPv4-mapped are returned as is.
+    pub fn apply(&self, addr: IpAddr) -> IpAddr {
+        match *self {
+            Ipv4Mapping::Unmapped => unmap(addr),
+            Ipv4Mapping::Mapped => {
+                match unmap(addr) {
+                    IpAddr::V4(v4) => IpAddr::V6(v4.to_ipv6_mapped()),
+                    addr => addr,
+                }
+            }
+        }
+    }
+}
+
+// Turns an IPv4-mapped IPv6 address into the IPv4 address it maps.
+// Unlike `Ipv6Addr::to_ipv4`, IPv4-compatible addresses are left alone.
+pub fn unmap(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => match mapped_ipv4(&v6) {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(v6),
+        },
+        addr => addr,
+    }
+}
+
+fn mapped_ipv4(addr: &Ipv6Addr) -> Option<Ipv4Addr> {
+    let s = addr.segments();
+    if s[..6] != [0, 0, 0, 0, 0, 0xffff] {
+        return None;
+    }
+
+    Some(Ipv4Addr::new((s[6] >> 8) as u8, s[6] as u8, (s[7] >> 8) as u8, s[7] as u8))
+}
+
+#[test]
+fn test_mapping() {
+    let v4 = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let mapped = IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0xffff, 0xc000, 0x0201));
+    let compatible = IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0xc000, 0x0201));
+
+    assert_eq!(Ipv4Mapping::Unmapped.apply(mapped), v4);
+    assert_eq!(Ipv4Mapping::Unmapped.apply(v4), v4);
+    assert_eq!(Ipv4Mapping::Unmapped.apply(compatible), compatible);
+    assert_eq!(Ipv4Mapping::Mapped.apply(v4), mapped);
+    assert_eq!(Ipv4Mapping::Mapped.apply(mapped), mapped);
+    assert_eq!(Ipv4Mapping::Mapped.apply(compatible), compatible);
+}
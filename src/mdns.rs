@@ -0,0 +1,324 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::str;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::{failed, finished, Future};
+use tokio_core::{LoopHandle, UdpSocket};
+use tokio_core::io::IoFuture;
+
+use super::udp::udp_recv_timeout;
+use super::Resolver;
+
+// The record types and class of the queries.
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+// Asks the responders to answer with unicast, to the port of the query.
+const UNICAST_RESPONSE: u16 = 0x8000;
+
+/// A resolver that resolves `.local` host names with multicast DNS.
+///
+/// For a host name in the `.local` domain, A and AAAA queries are sent to
+/// the mDNS groups `224.0.0.251:5353` and `[ff02::fb]:5353`, and the
+/// addresses answered on either during the collection window are
+/// returned. `ff02::fb` is link-local, so it is reached through the
+/// interface set with `ipv6_interface`. If one of the groups can't be
+/// queried, on a host without IPv6 for instance, the other one is still
+/// used. It fails with an error of kind `NotFound` if nothing answers in
+/// time. The other host names are resolved by the inner resolver.
+///
+/// IPv6 addresses answered are returned as is, so a link-local one is
+/// only usable if the system picks the right interface for it.
+#[derive(Clone)]
+pub struct MdnsResolver<R> {
+    handle: LoopHandle,
+    resolver: R,
+    window: Duration,
+    interface: u32,
+}
+
+impl<R> MdnsResolver<R> where R: Resolver {
+    /// Create a new MdnsResolver, collecting answers for 250 milliseconds.
+    pub fn new(handle: LoopHandle, resolver: R) -> Self {
+        MdnsResolver {
+            handle: handle,
+            resolver: resolver,
+            window: Duration::from_millis(250),
+            interface: 0,
+        }
+    }
+
+    /// Set the index of the interface the IPv6 queries are sent on.
+    ///
+    /// It is the scope id of `ff02::fb`. By default it is 0, which leaves
+    /// the choice of the interface to the system.
+    pub fn ipv6_interface(mut self, index: u32) -> Self {
+        self.interface = index;
+        self
+    }
+
+    /// Set how long answers are collected for.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+}
+
+impl<R> Resolver for MdnsResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let name = host.trim_right_matches('.').to_lowercase();
+        if !name.ends_with(".local") {
+            return self.resolver.resolve(host);
+        }
+
+        let query = match encode_query(&name) {
+            Ok(query) => query,
+            Err(e) => return failed(e).boxed(),
+        };
+
+        let deadline = Instant::now() + self.window;
+
+        let v4 = query_group(self.handle.clone(),
+                             SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0),
+                             SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353),
+                             query.clone(), name.clone(), deadline);
+        let v6 = query_group(self.handle.clone(),
+                             SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)), 0),
+                             SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb), 5353, 0,
+                                                              self.interface)),
+                             query, name, deadline);
+
+        v4.join(v6).and_then(|(v4, v6)| merge_answers(v4, v6)).boxed()
+    }
+}
+
+// Merges the answers of both groups, the query fails only if both of them failed.
+fn merge_answers(v4: io::Result<Vec<IpAddr>>, v6: io::Result<Vec<IpAddr>>) -> io::Result<Vec<IpAddr>> {
+    let addrs = match (v4, v6) {
+        (Err(e), Err(_)) => return Err(e),
+        (Ok(addrs), Err(_)) | (Err(_), Ok(addrs)) => addrs,
+        (Ok(mut addrs), Ok(v6)) => {
+            for addr in v6 {
+                if !addrs.contains(&addr) {
+                    addrs.push(addr);
+                }
+            }
+            addrs
+        }
+    };
+
+    if addrs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no multicast DNS answer for the host"));
+    }
+    Ok(addrs)
+}
+
+// Queries one mDNS group and collects its answers. An error of the group
+// is given back as the item, so that the other group can still be used.
+fn query_group(handle: LoopHandle, bind_addr: SocketAddr, group: SocketAddr, query: Vec<u8>, name: String,
+               deadline: Instant) -> IoFuture<io::Result<Vec<IpAddr>>>
+{
+    debug!("querying {} with multicast DNS on {}", name, group);
+
+    let collect_handle = handle.clone();
+    handle.udp_bind(&bind_addr).and_then(move |socket| {
+        if let Err(e) = socket.send_to(&query, &group) {
+            return failed(e).boxed();
+        }
+        collect_answers(collect_handle, Arc::new(socket), name, deadline, Vec::new())
+    }).then(move |res| {
+        if let Err(ref e) = res {
+            debug!("could not query the multicast DNS group {}: {}", group, e);
+        }
+        Ok(res)
+    }).boxed()
+}
+
+// Receives the answers until the deadline.
+fn collect_answers(handle: LoopHandle, socket: Arc<UdpSocket>, name: String, deadline: Instant,
+                   mut addrs: Vec<IpAddr>) -> IoFuture<Vec<IpAddr>>
+{
+    let now = Instant::now();
+    if now >= deadline {
+        return finished(addrs).boxed();
+    }
+
+    udp_recv_timeout(handle.clone(), socket.clone(), deadline - now).then(move |res| {
+        match res {
+            Ok((msg, from)) => {
+                for addr in parse_answers(&msg, &name) {
+                    debug!("{} answered {} for {}", from, addr, name);
+                    if !addrs.contains(&addr) {
+                        addrs.push(addr);
+                    }
+                }
+                collect_answers(handle, socket, name, deadline, addrs)
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => finished(addrs).boxed(),
+            Err(e) => failed(e).boxed(),
+        }
+    }).boxed()
+}
+
+fn push_u16(msg: &mut Vec<u8>, value: u16) {
+    msg.push((value >> 8) as u8);
+    msg.push(value as u8);
+}
+
+fn encode_query(name: &str) -> io::Result<Vec<u8>> {
+    // An ID and flags of zero, and two questions.
+    let mut msg = vec![0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0];
+
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid host name"));
+        }
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+    push_u16(&mut msg, TYPE_A);
+    push_u16(&mut msg, CLASS_IN | UNICAST_RESPONSE);
+
+    // The second question points to the name of the first one.
+    msg.extend_from_slice(&[0xc0, 12]);
+    push_u16(&mut msg, TYPE_AAAA);
+    push_u16(&mut msg, CLASS_IN | UNICAST_RESPONSE);
+
+    Ok(msg)
+}
+
+fn malformed() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "malformed multicast DNS response")
+}
+
+fn read_slice(msg: &[u8], pos: usize, len: usize) -> io::Result<&[u8]> {
+    if pos + len > msg.len() {
+        return Err(malformed());
+    }
+    Ok(&msg[pos..pos + len])
+}
+
+fn read_u16(msg: &[u8], pos: usize) -> io::Result<u16> {
+    let bytes = try!(read_slice(msg, pos, 2));
+    Ok((bytes[0] as u16) << 8 | bytes[1] as u16)
+}
+
+// Reads a possibly compressed name, returns it with the position after it.
+fn read_name(msg: &[u8], mut pos: usize) -> io::Result<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = try!(read_slice(msg, pos, 1))[0] as usize;
+
+        if len == 0 {
+            let end = end.unwrap_or(pos + 1);
+            return Ok((labels.join("."), end));
+        } else if len & 0xc0 == 0xc0 {
+            let ptr = try!(read_u16(msg, pos)) as usize & 0x3fff;
+            if end.is_none() {
+                end = Some(pos + 2);
+            }
+            // Protects against pointer loops.
+            jumps += 1;
+            if jumps > 16 {
+                return Err(malformed());
+            }
+            pos = ptr;
+        } else {
+            let label = try!(read_slice(msg, pos + 1, len));
+            let label = try!(str::from_utf8(label).map_err(|_| malformed()));
+            labels.push(label.to_lowercase());
+            pos += 1 + len;
+        }
+    }
+}
+
+// Returns the addresses of `name` in a response, malformed responses are ignored.
+fn parse_answers(msg: &[u8], name: &str) -> Vec<IpAddr> {
+    parse_records(msg, name).unwrap_or(Vec::new())
+}
+
+fn parse_records(msg: &[u8], name: &str) -> io::Result<Vec<IpAddr>> {
+    let flags = try!(read_u16(msg, 2));
+    if flags & 0x8000 == 0 {
+        // This is a query, not a response.
+        return Ok(Vec::new());
+    }
+
+    let questions = try!(read_u16(msg, 4));
+    let records = try!(read_u16(msg, 6)) as usize + try!(read_u16(msg, 8)) as usize +
+                  try!(read_u16(msg, 10)) as usize;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        let (_, next) = try!(read_name(msg, pos));
+        pos = next + 4;
+    }
+
+    let mut addrs = Vec::new();
+    for _ in 0..records {
+        let (owner, next) = try!(read_name(msg, pos));
+        let rtype = try!(read_u16(msg, next));
+        // The top bit of the class is the cache flush bit.
+        let class = try!(read_u16(msg, next + 2)) & !0x8000;
+        let len = try!(read_u16(msg, next + 8)) as usize;
+        let data = try!(read_slice(msg, next + 10, len));
+        pos = next + 10 + len;
+
+        if owner != name || class != CLASS_IN {
+            continue;
+        }
+
+        if rtype == TYPE_A && len == 4 {
+            addrs.push(IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])));
+        } else if rtype == TYPE_AAAA && len == 16 {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(data);
+            addrs.push(IpAddr::V6(Ipv6Addr::from(octets)));
+        }
+    }
+
+    Ok(addrs)
+}
+
+#[test]
+fn test_encode_query() {
+    let query = encode_query("printer.local").unwrap();
+    let mut expected = vec![0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0];
+    expected.extend_from_slice(b"\x07printer\x05local\x00\x00\x01\x80\x01\xc0\x0c\x00\x1c\x80\x01");
+    assert_eq!(query, expected);
+
+    assert!(encode_query("a..local").is_err());
+}
+
+#[test]
+fn test_parse_answers() {
+    // A response with the name in the answer, and a pointer to it in the additional record.
+    let mut msg = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 1];
+    msg.extend_from_slice(b"\x07Printer\x05local\x00\x00\x01\x80\x01\x00\x00\x00\x78\x00\x04\xc0\xa8\x00\x07");
+    msg.extend_from_slice(b"\xc0\x0c\x00\x1c\x80\x01\x00\x00\x00\x78\x00\x10");
+    msg.extend_from_slice(&[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7]);
+
+    assert_eq!(parse_answers(&msg, "printer.local"),
+               vec![IpAddr::V4(Ipv4Addr::new(192, 168, 0, 7)),
+                    IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 7))]);
+    assert!(parse_answers(&msg, "scanner.local").is_empty());
+    assert!(parse_answers(&msg[..20], "printer.local").is_empty());
+}
+
+#[test]
+fn test_merge_answers() {
+    let a = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 7));
+    let b = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 7));
+    let unreachable = || Err(io::Error::new(io::ErrorKind::Other, "network unreachable"));
+
+    assert_eq!(merge_answers(Ok(vec![a]), Ok(vec![a, b])).unwrap(), vec![a, b]);
+    assert_eq!(merge_answers(unreachable(), Ok(vec![b])).unwrap(), vec![b]);
+    assert_eq!(merge_answers(Ok(vec![]), unreachable()).unwrap_err().kind(), io::ErrorKind::NotFound);
+    assert_eq!(merge_answers(unreachable(), unreachable()).unwrap_err().kind(), io::ErrorKind::Other);
+}
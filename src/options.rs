@@ -0,0 +1,136 @@
+use std::io;
+use std::time::Duration;
+
+use tokio_core::TcpStream;
+
+#[cfg(unix)]
+use super::dscp::set_dscp;
+#[cfg(target_os = "linux")]
+use super::linux::{set_quickack, set_user_timeout};
+#[cfg(unix)]
+use super::timeouts::set_socket_timeouts;
+
+/// The options set on a connection once it is established.
+///
+/// Every option is left to the system default unless its method is
+/// called. `Connector` sets its options on the connections it creates
+/// with one of these, and `connect_to_addrs` takes one too.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SocketOptions {
+    timeouts: Option<(Option<Duration>, Option<Duration>)>,
+    dscp: Option<u8>,
+    nodelay: Option<bool>,
+    quickack: Option<bool>,
+    user_timeout: Option<Duration>,
+}
+
+impl SocketOptions {
+    /// Create a new `SocketOptions` with every option left to the system default.
+    pub fn new() -> Self {
+        SocketOptions::default()
+    }
+
+    /// Set the read and write timeouts, with `set_socket_timeouts`.
+    ///
+    /// This method is only available on Unix platforms.
+    #[cfg(unix)]
+    pub fn socket_timeouts(mut self, read: Option<Duration>, write: Option<Duration>) -> Self {
+        self.timeouts = Some((read, write));
+        self
+    }
+
+    /// Set the DSCP marking, with `set_dscp`.
+    ///
+    /// This method is only available on Unix platforms.
+    #[cfg(unix)]
+    pub fn dscp(mut self, dscp: u8) -> Self {
+        self.dscp = Some(dscp);
+        self
+    }
+
+    /// Enable or disable Nagle's algorithm, with `TCP_NODELAY`.
+    pub fn nodelay(mut self, enabled: bool) -> Self {
+        self.nodelay = Some(enabled);
+        self
+    }
+
+    /// Enable or disable the quick acknowledgement mode, with `set_quickack`.
+    ///
+    /// This method is only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn quickack(mut self, enabled: bool) -> Self {
+        self.quickack = Some(enabled);
+        self
+    }
+
+    /// Set the user timeout, with `set_user_timeout`.
+    ///
+    /// The option only exists on Linux: elsewhere, `check` fails when it
+    /// is set.
+    pub fn user_timeout(mut self, timeout: Duration) -> Self {
+        self.user_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail if one of the options can't be set on this platform.
+    ///
+    /// This is meant to be called before connecting, so nothing is
+    /// connected to only to be dropped by `apply`.
+    #[cfg(target_os = "linux")]
+    pub fn check(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Fail if one of the options can't be set on this platform.
+    ///
+    /// This is meant to be called before connecting, so nothing is
+    /// connected to only to be dropped by `apply`.
+    #[cfg(not(target_os = "linux"))]
+    pub fn check(&self) -> io::Result<()> {
+        if self.user_timeout.is_some() {
+            return Err(io::Error::new(io::ErrorKind::Other, "TCP_USER_TIMEOUT is only supported on Linux"));
+        }
+        Ok(())
+    }
+
+    /// Set the options on `stream`.
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        if let Some(enabled) = self.nodelay {
+            try!(stream.set_nodelay(enabled));
+        }
+        try!(self.apply_unix(stream));
+        self.apply_linux(stream)
+    }
+
+    #[cfg(unix)]
+    fn apply_unix(&self, stream: &TcpStream) -> io::Result<()> {
+        if let Some(dscp) = self.dscp {
+            try!(set_dscp(stream, dscp));
+        }
+        match self.timeouts {
+            Some((read, write)) => set_socket_timeouts(stream, read, write),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn apply_unix(&self, _: &TcpStream) -> io::Result<()> {
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn apply_linux(&self, stream: &TcpStream) -> io::Result<()> {
+        if let Some(enabled) = self.quickack {
+            try!(set_quickack(stream, enabled));
+        }
+        match self.user_timeout {
+            Some(timeout) => set_user_timeout(stream, timeout),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn apply_linux(&self, _: &TcpStream) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::net::{IpAddr, SocketAddr};
+
+use futures::Future;
+use tokio_core::TcpStream;
+use tokio_core::io::{flush, write_all, IoFuture};
+
+const SIGNATURE: &'static [u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// A PROXY protocol version 2 header.
+///
+/// The header announces the original source and destination of a proxied
+/// TCP connection to the server. When one address is IPv4 and the other is
+/// IPv6, the IPv4 address is sent as an IPv4-mapped IPv6 address.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProxyHeader {
+    /// The address of the original client.
+    pub source: SocketAddr,
+    /// The address the original client connected to.
+    pub destination: SocketAddr,
+}
+
+impl ProxyHeader {
+    /// Create a new header with the given source and destination.
+    pub fn new(source: SocketAddr, destination: SocketAddr) -> Self {
+        ProxyHeader {
+            source: source,
+            destination: destination,
+        }
+    }
+
+    /// Encode the header in the binary format of the protocol.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(16 + 36);
+        buf.extend_from_slice(SIGNATURE);
+        // version 2, PROXY command
+        buf.push(0x21);
+
+        match (self.source.ip(), self.destination.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                // TCP over IPv4
+                buf.push(0x11);
+                push_u16(&mut buf, 12);
+                buf.extend_from_slice(&src.octets());
+                buf.extend_from_slice(&dst.octets());
+            }
+            (src, dst) => {
+                // TCP over IPv6
+                buf.push(0x21);
+                push_u16(&mut buf, 36);
+                for addr in &[src, dst] {
+                    let addr = match *addr {
+                        IpAddr::V4(addr) => addr.to_ipv6_mapped(),
+                        IpAddr::V6(addr) => addr,
+                    };
+                    for segment in &addr.segments() {
+                        push_u16(&mut buf, *segment);
+                    }
+                }
+            }
+        }
+
+        push_u16(&mut buf, self.source.port());
+        push_u16(&mut buf, self.destination.port());
+        buf
+    }
+}
+
+fn push_u16(buf: &mut Vec<u8>, val: u16) {
+    buf.push((val >> 8) as u8);
+    buf.push(val as u8);
+}
+
+/// Write a PROXY protocol version 2 header on a stream.
+///
+/// This is meant to be chained to the future of a connection helper, such
+/// as `tcp_connect_par`, or done by `DnsSupport::tcp_connect_with_proxy_header`.
+/// The future resolves into the stream once the header has been written and
+/// flushed.
+pub fn write_proxy_header(stream: TcpStream, header: &ProxyHeader) -> IoFuture<TcpStream> {
+    write_all(stream, header.encode()).and_then(|(stream, _)| flush(stream)).boxed()
+}
+
+// Chains the header to the connection to the server.
+pub fn with_proxy_header(connect: IoFuture<TcpStream>, header: &ProxyHeader) -> IoFuture<TcpStream> {
+    let header = *header;
+    connect.and_then(move |stream| write_proxy_header(stream, &header)).boxed()
+}
+
+#[test]
+fn test_encode_v4() {
+    let header = ProxyHeader::new("192.168.0.1:56324".parse().unwrap(), "10.0.0.1:443".parse().unwrap());
+
+    let mut expected = SIGNATURE.to_vec();
+    expected.extend_from_slice(&[0x21, 0x11, 0, 12,
+                                 192, 168, 0, 1,
+                                 10, 0, 0, 1,
+                                 0xdc, 0x04,
+                                 0x01, 0xbb]);
+    assert_eq!(header.encode(), expected);
+}
+
+#[test]
+fn test_encode_v6() {
+    let header = ProxyHeader::new("[2001:db8::1]:1227".parse().unwrap(), "10.0.0.1:443".parse().unwrap());
+
+    let mut expected = SIGNATURE.to_vec();
+    expected.extend_from_slice(&[0x21, 0x21, 0, 36,
+                                 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+                                 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 10, 0, 0, 1,
+                                 0x04, 0xcb,
+                                 0x01, 0xbb]);
+    assert_eq!(header.encode(), expected);
+}
+
+#[test]
+fn test_with_proxy_header() {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use tokio_core::Loop;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let header = ProxyHeader::new("192.168.0.1:56324".parse().unwrap(), addr);
+
+    let mut lp = Loop::new().unwrap();
+    let connect = with_proxy_header(lp.handle().tcp_connect(&addr), &header);
+    lp.run(connect).unwrap();
+
+    let (mut server, _) = listener.accept().unwrap();
+    let mut received = vec![0; header.encode().len()];
+    server.read_exact(&mut received).unwrap();
+    assert_eq!(received, header.encode());
+}
@@ -0,0 +1,115 @@
+use std::io;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use futures::{finished, Future};
+use tokio_core::LoopHandle;
+use tokio_core::io::IoFuture;
+
+use super::common::sleep;
+use super::Resolver;
+
+/// A resolver that races a preferred resolver against a fallback.
+///
+/// The host name is given to both resolvers at the same time. If the
+/// `preferred` resolver answers first, its answer is returned. If the
+/// `fallback` resolver answers first, the `preferred` resolver is given
+/// up to `window` more to answer before the answer of the `fallback`
+/// resolver is returned. If one of the resolvers fails, the answer of
+/// the other one is awaited.
+#[derive(Clone)]
+pub struct RaceResolver<A, B> {
+    handle: LoopHandle,
+    preferred: A,
+    fallback: B,
+    window: Duration,
+}
+
+impl<A, B> RaceResolver<A, B>
+    where A: Resolver,
+          B: Resolver
+{
+    /// Create a new RaceResolver from the two given resolvers.
+    pub fn new(handle: LoopHandle, preferred: A, fallback: B, window: Duration) -> Self {
+        RaceResolver {
+            handle: handle,
+            preferred: preferred,
+            fallback: fallback,
+            window: window,
+        }
+    }
+}
+
+impl<A, B> Resolver for RaceResolver<A, B>
+    where A: Resolver,
+          B: Resolver
+{
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let handle = self.handle.clone();
+        let window = self.window;
+        let host = host.to_owned();
+
+        // Both answers are turned into items, so that the first one to
+        // complete is known even when it is an error.
+        let preferred = self.preferred.resolve(&host).then(|res| Ok::<_, io::Error>((true, res)));
+        let fallback = self.fallback.resolve(&host).then(|res| Ok::<_, io::Error>((false, res)));
+
+        preferred.select(fallback).map_err(|(e, _)| e).and_then(move |((is_preferred, res), other)| {
+            match (is_preferred, res) {
+                (true, Ok(addrs)) => finished(addrs).boxed(),
+                (false, Ok(addrs)) => {
+                    debug!("fallback resolver answered first for {}, waiting {:?} for the preferred one",
+                           host, window);
+
+                    let timeout_addrs = addrs.clone();
+                    let timeout = sleep(handle, window).map(move |()| timeout_addrs);
+
+                    other.map(move |(_, res)| res.unwrap_or(addrs))
+                         .select(timeout)
+                         .map(|(addrs, _)| addrs)
+                         .map_err(|(e, _)| e)
+                         .boxed()
+                }
+                (_, Err(e)) => {
+                    debug!("a resolver failed for {}, waiting for the other one: {}", host, e);
+                    other.and_then(|(_, res)| res).boxed()
+                }
+            }
+        }).boxed()
+    }
+}
+
+#[test]
+fn test_race() {
+    use std::net::Ipv4Addr;
+    use futures::failed;
+    use tokio_core::Loop;
+
+    struct Fixed(u8);
+
+    impl Resolver for Fixed {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, self.0))]).boxed()
+        }
+    }
+
+    struct Failing;
+
+    impl Resolver for Failing {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            failed(io::Error::new(io::ErrorKind::Other, "failing")).boxed()
+        }
+    }
+
+    let lp = Loop::new().unwrap();
+    let window = Duration::from_millis(10);
+
+    let resolver = RaceResolver::new(lp.handle(), Fixed(1), Fixed(2), window);
+    assert_eq!(resolver.resolve("example.com").wait().unwrap(), vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+
+    let resolver = RaceResolver::new(lp.handle(), Failing, Fixed(2), window);
+    assert_eq!(resolver.resolve("example.com").wait().unwrap(), vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))]);
+
+    let resolver = RaceResolver::new(lp.handle(), Failing, Failing, window);
+    assert!(resolver.resolve("example.com").wait().is_err());
+}
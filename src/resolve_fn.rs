@@ -0,0 +1,43 @@
+use std::net::IpAddr;
+
+use tokio_core::{LoopHandle, TcpStream};
+use tokio_core::io::IoFuture;
+
+use super::common::tcp_connect_seq;
+use super::{Resolver, ToEndpoint};
+
+// Adapts a resolution closure to the Resolver trait.
+struct FnResolver<F>(F);
+
+impl<F> Resolver for FnResolver<F> where F: Fn(&str) -> IoFuture<Vec<IpAddr>> {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        (self.0)(host)
+    }
+}
+
+/// Create a new TcpStream connected to the specified endpoint, resolving it with a closure.
+///
+/// This behaves like `DnsSupport::tcp_connect_seq`, except that a host
+/// name is resolved by calling `resolve` with it instead of a resolver.
+/// It is convenient when the addresses of a name are computed at the
+/// time of the connect, for a single call site.
+pub fn tcp_connect_with<'a, T, F>(handle: LoopHandle, ep: T, resolve: F) -> IoFuture<TcpStream>
+    where T: ToEndpoint<'a>,
+          F: Fn(&str) -> IoFuture<Vec<IpAddr>>
+{
+    tcp_connect_seq(handle, FnResolver(resolve), ep)
+}
+
+#[test]
+fn test_fn_resolver() {
+    use std::net::Ipv4Addr;
+    use futures::{finished, Future};
+
+    let resolver = FnResolver(|host: &str| {
+        let last = if host == "a.example.com" { 1 } else { 2 };
+        finished(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, last))]).boxed()
+    });
+
+    assert_eq!(resolver.resolve("a.example.com").wait().unwrap(), vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+    assert_eq!(resolver.resolve("b.example.com").wait().unwrap(), vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))]);
+}
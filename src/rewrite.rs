@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use std::io;
+use std::sync::Arc;
+
+use super::{Endpoint, EndpointBuf, ToEndpoint};
+
+/// The EndpointRewriter trait represents an object capable of
+/// rewriting endpoints before they are resolved.
+///
+/// Give one to a `Connector` with `Connector::rewriter` to apply aliases
+/// or environment-specific suffixes to every endpoint it connects to.
+pub trait EndpointRewriter {
+    /// Given an endpoint, this function returns the endpoint to use
+    /// instead, or `None` to keep it as is.
+    fn rewrite(&self, ep: Endpoint) -> Option<EndpointBuf>;
+}
+
+enum Converted<'a> {
+    Original(Endpoint<'a>),
+    Rewritten(EndpointBuf),
+    // The error is taken by the conversion.
+    Failed(RefCell<Option<io::Error>>),
+}
+
+// An endpoint converted and rewritten ahead of time. A reference to it
+// converts into the endpoint, which may borrow the rewritten host name.
+pub struct Rewritten<'a> {
+    converted: Converted<'a>,
+}
+
+impl<'a> Rewritten<'a> {
+    pub fn new<T>(ep: T, rewriter: Option<&Arc<EndpointRewriter + Send + Sync>>) -> Self
+        where T: ToEndpoint<'a>
+    {
+        let ep = match ep.to_endpoint() {
+            Ok(ep) => ep,
+            Err(e) => return Rewritten { converted: Converted::Failed(RefCell::new(Some(e))) },
+        };
+
+        let converted = match rewriter.and_then(|rewriter| rewriter.rewrite(ep)) {
+            Some(rewritten) => {
+                debug!("rewrote {} into {}", ep, rewritten);
+                Converted::Rewritten(rewritten)
+            }
+            None => Converted::Original(ep),
+        };
+
+        Rewritten { converted: converted }
+    }
+}
+
+impl<'s, 'a> ToEndpoint<'s> for &'s Rewritten<'a> {
+    fn to_endpoint(self) -> io::Result<Endpoint<'s>> {
+        match self.converted {
+            Converted::Original(ep) => Ok(ep),
+            Converted::Rewritten(ref ep) => Ok(ep.as_endpoint()),
+            Converted::Failed(ref e) => {
+                Err(e.borrow_mut().take().unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::Other, "the endpoint is invalid")
+                }))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_rewrite() {
+    struct Suffix;
+
+    impl EndpointRewriter for Suffix {
+        fn rewrite(&self, ep: Endpoint) -> Option<EndpointBuf> {
+            match ep {
+                Endpoint::Host(host, port) => Some(EndpointBuf::Host(format!("{}.staging", host), port)),
+                Endpoint::SocketAddr(_) => None,
+            }
+        }
+    }
+
+    let rewriter: Arc<EndpointRewriter + Send + Sync> = Arc::new(Suffix);
+
+    let rewritten = Rewritten::new("db:5432", Some(&rewriter));
+    assert_eq!((&rewritten).to_endpoint().unwrap(), Endpoint::Host("db.staging", 5432));
+
+    let rewritten = Rewritten::new("127.0.0.1:5432", Some(&rewriter));
+    assert_eq!((&rewritten).to_endpoint().unwrap(), "127.0.0.1:5432".to_endpoint().unwrap());
+
+    let rewritten = Rewritten::new("invalid", Some(&rewriter));
+    assert!((&rewritten).to_endpoint().is_err());
+}
@@ -0,0 +1,90 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A resolver that dispatches host names to other resolvers.
+///
+/// Each route associates a domain suffix to a resolver. A host name is
+/// given to the resolver of the longest matching suffix, or to the default
+/// resolver if no suffix matches. Suffixes match whole labels and are
+/// compared case-insensitively, so the suffix `internal` matches
+/// `db.Internal` but not `db.notinternal`.
+#[derive(Clone)]
+pub struct RoutingResolver {
+    routes: Vec<(String, Arc<Resolver + Send + Sync>)>,
+    default: Arc<Resolver + Send + Sync>,
+}
+
+impl RoutingResolver {
+    /// Create a new RoutingResolver using `default` for unmatched host names.
+    pub fn new<R>(default: R) -> Self
+        where R: Resolver + Send + Sync + 'static
+    {
+        RoutingResolver {
+            routes: Vec::new(),
+            default: Arc::new(default),
+        }
+    }
+
+    /// Add a route sending host names ending with `suffix` to `resolver`.
+    pub fn route<R>(mut self, suffix: &str, resolver: R) -> Self
+        where R: Resolver + Send + Sync + 'static
+    {
+        let suffix = suffix.trim_left_matches('.').trim_right_matches('.').to_lowercase();
+        self.routes.push((suffix, Arc::new(resolver)));
+        self
+    }
+
+    fn select(&self, host: &str) -> &Resolver {
+        let host = host.trim_right_matches('.').to_lowercase();
+        let mut best: Option<&(String, Arc<Resolver + Send + Sync>)> = None;
+
+        for route in self.routes.iter() {
+            let suffix = &route.0;
+            let matches = host == *suffix ||
+                          (host.ends_with(&suffix[..]) && host[..host.len() - suffix.len()].ends_with('.'));
+
+            if matches && best.map_or(true, |best| suffix.len() > best.0.len()) {
+                best = Some(route);
+            }
+        }
+
+        match best {
+            Some(route) => &*route.1,
+            None => &*self.default,
+        }
+    }
+}
+
+impl Resolver for RoutingResolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        self.select(host).resolve(host)
+    }
+}
+
+#[test]
+fn test_routing() {
+    use std::net::Ipv4Addr;
+    use futures::{finished, Future};
+
+    struct Fixed(u8);
+
+    impl Resolver for Fixed {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, self.0))]).boxed()
+        }
+    }
+
+    let resolver = RoutingResolver::new(Fixed(0))
+        .route(".internal", Fixed(1))
+        .route("db.internal", Fixed(2));
+
+    let resolve = |host| resolver.resolve(host).wait().unwrap()[0];
+    assert_eq!(resolve("example.com"), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)));
+    assert_eq!(resolve("web.Internal."), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+    assert_eq!(resolve("a.DB.internal"), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+    assert_eq!(resolve("notinternal"), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)));
+}
@@ -13,19 +13,25 @@ impl<A> Future for SelectAllOk<A>
     type Error = ();
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        let mut has_not_ready = false;
+        let mut i = 0;
 
-        for f in self.inner.iter_mut() {
-            match f.poll() {
-                Poll::Ok(x) => return Poll::Ok(x),
-                Poll::NotReady => {
-                    has_not_ready = true;
+        while i < self.inner.len() {
+            match self.inner[i].poll() {
+                Poll::Ok(x) => {
+                    // The other futures are dropped before the item is
+                    // returned, so none of them outlives the winner.
+                    self.inner.clear();
+                    return Poll::Ok(x);
                 }
-                _ => {}
+                Poll::Err(_) => {
+                    // A failed future is never polled again.
+                    self.inner.remove(i);
+                }
+                Poll::NotReady => i += 1,
             }
         }
 
-        if has_not_ready {
+        if !self.inner.is_empty() {
             // at least one of the underlying futures is not finished
             Poll::NotReady
         } else {
@@ -80,3 +86,51 @@ fn test_not_ready() {
         _ => panic!(),
     }
 }
+
+#[test]
+fn test_losers_dropped() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Tracked {
+        ready: bool,
+        alive: Arc<AtomicUsize>,
+    }
+
+    impl Tracked {
+        fn new(ready: bool, alive: &Arc<AtomicUsize>) -> Self {
+            alive.fetch_add(1, Ordering::SeqCst);
+            Tracked { ready: ready, alive: alive.clone() }
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.alive.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    impl Future for Tracked {
+        type Item = ();
+        type Error = ();
+
+        fn poll(&mut self) -> Poll<(), ()> {
+            if self.ready {
+                Poll::Ok(())
+            } else {
+                Poll::NotReady
+            }
+        }
+    }
+
+    let alive = Arc::new(AtomicUsize::new(0));
+    let mut fut = select_all_ok(vec![Tracked::new(false, &alive),
+                                     Tracked::new(true, &alive),
+                                     Tracked::new(false, &alive)]);
+
+    // Every future, the winner included, is dropped when the item is returned.
+    match fut.poll() {
+        Poll::Ok(()) => assert_eq!(alive.load(Ordering::SeqCst), 0),
+        _ => panic!(),
+    }
+}
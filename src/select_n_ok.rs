@@ -0,0 +1,101 @@
+use std::mem;
+
+use futures::{IntoFuture, Future, Poll};
+
+pub struct SelectNOk<A>
+    where A: Future
+{
+    inner: Vec<A>,
+    n: usize,
+    items: Vec<A::Item>,
+    errors: Vec<A::Error>,
+}
+
+impl<A> Future for SelectNOk<A>
+    where A: Future
+{
+    type Item = (Vec<A::Item>, Vec<A::Error>);
+    type Error = Vec<A::Error>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let mut idx = 0;
+
+        while self.items.len() < self.n && idx < self.inner.len() {
+            match self.inner[idx].poll() {
+                Poll::Ok(x) => {
+                    self.inner.swap_remove(idx);
+                    self.items.push(x);
+                }
+                Poll::Err(e) => {
+                    self.inner.swap_remove(idx);
+                    self.errors.push(e);
+                }
+                Poll::NotReady => {
+                    idx += 1;
+                }
+            }
+        }
+
+        if self.items.len() < self.n && !self.inner.is_empty() {
+            // we still need more items and some futures are not finished
+            return Poll::NotReady;
+        }
+
+        // either we have enough items or all the futures are finished,
+        // the remaining futures are dropped
+        self.inner.clear();
+
+        let items = mem::replace(&mut self.items, Vec::new());
+        let errors = mem::replace(&mut self.errors, Vec::new());
+
+        if items.is_empty() && self.n > 0 {
+            Poll::Err(errors)
+        } else {
+            Poll::Ok((items, errors))
+        }
+    }
+}
+
+pub fn select_n_ok<I>(iter: I, n: usize) -> SelectNOk<<I::Item as IntoFuture>::Future>
+    where I: IntoIterator,
+          I::Item: IntoFuture
+{
+    SelectNOk {
+        inner: iter.into_iter().map(|a| a.into_future()).collect(),
+        n: n,
+        items: Vec::new(),
+        errors: Vec::new(),
+    }
+}
+
+#[test]
+fn test_enough() {
+    use futures::{self, BoxFuture};
+
+    let futs: Vec<BoxFuture<i32, ()>> = vec![futures::finished(1).boxed(),
+                                             futures::failed(()).boxed(),
+                                             futures::finished(2).boxed(),
+                                             futures::finished(3).boxed()];
+    let (items, _) = select_n_ok(futs, 2).wait().unwrap();
+    assert_eq!(items.len(), 2);
+}
+
+#[test]
+fn test_partial() {
+    use futures::{self, BoxFuture};
+
+    let futs: Vec<BoxFuture<i32, ()>> = vec![futures::finished(1).boxed(),
+                                             futures::failed(()).boxed()];
+    let res = select_n_ok(futs, 2).wait();
+    assert_eq!(res, Ok((vec![1], vec![()])));
+}
+
+#[test]
+fn test_err() {
+    use futures::{self, BoxFuture};
+
+    let futs: Vec<BoxFuture<i32, ()>> = vec![futures::failed(()).boxed(),
+                                             futures::failed(()).boxed()];
+    let res = select_n_ok(futs, 2).wait();
+    assert_eq!(res, Err(vec![(), ()]));
+}
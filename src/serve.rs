@@ -0,0 +1,105 @@
+use std::io;
+use std::net::SocketAddr;
+
+use futures::{failed, Future, Poll};
+use futures::stream::Stream;
+use tokio_core::{LoopHandle, TcpListener, TcpStream};
+use tokio_core::io::{IoFuture, IoStream};
+
+use super::common::{no_addresses, resolve_endpoint};
+use super::select_n_ok::select_n_ok;
+use super::{Resolver, ToEndpoint};
+
+// Stream which binds listeners and merges their incoming connections.
+struct Serve {
+    bind: Option<IoFuture<Vec<TcpListener>>>,
+    incoming: Vec<IoStream<(TcpStream, SocketAddr)>>,
+    next: usize,
+}
+
+impl Stream for Serve {
+    type Item = (TcpStream, SocketAddr);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        if let Some(mut bind) = self.bind.take() {
+            match bind.poll() {
+                Poll::Ok(listeners) => {
+                    self.incoming = listeners.into_iter().map(|listener| listener.incoming()).collect();
+                }
+                Poll::Err(e) => return Poll::Err(e),
+                Poll::NotReady => {
+                    self.bind = Some(bind);
+                    return Poll::NotReady;
+                }
+            }
+        }
+
+        // The listeners are polled in turns, starting after the one which
+        // produced the last item, so that a busy listener can't starve the others.
+        let mut remaining = self.incoming.len();
+        while remaining > 0 && !self.incoming.is_empty() {
+            remaining -= 1;
+
+            let idx = self.next % self.incoming.len();
+            self.next = idx + 1;
+
+            match self.incoming[idx].poll() {
+                Poll::Ok(Some(item)) => return Poll::Ok(Some(item)),
+                Poll::Ok(None) => {
+                    self.incoming.remove(idx);
+                    self.next = idx;
+                }
+                Poll::Err(e) => return Poll::Err(e),
+                Poll::NotReady => {}
+            }
+        }
+
+        if self.incoming.is_empty() {
+            Poll::Ok(None)
+        } else {
+            Poll::NotReady
+        }
+    }
+}
+
+pub fn tcp_serve<'a, R, T>(handle: LoopHandle, resolver: R, ep: T) -> IoStream<(TcpStream, SocketAddr)>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    // The endpoint is kept so it can be named in the errors.
+    let resolve = match ep.to_endpoint() {
+        Ok(ep) => {
+            let resolve = resolve_endpoint(resolver, ep);
+            let ep = ep.to_buf();
+            resolve.map(move |addrs| (ep, addrs)).boxed()
+        }
+        Err(e) => failed(e).boxed(),
+    };
+
+    let bind = resolve.and_then(move |(ep, addrs)| {
+        if addrs.is_empty() {
+            return failed(no_addresses(&ep)).boxed();
+        }
+
+        debug!("binding {} listeners", addrs.len());
+
+        let n = addrs.len();
+        let futs = addrs.into_iter().map(|addr| handle.clone().tcp_listen(&addr));
+
+        select_n_ok(futs, n).map(|(listeners, errors)| {
+            for e in errors {
+                warn!("could not bind a listener: {}", e);
+            }
+            listeners
+        }).map_err(move |errors| {
+            errors.into_iter().next().unwrap_or_else(|| no_addresses(&ep))
+        }).boxed()
+    }).boxed();
+
+    Serve {
+        bind: Some(bind),
+        incoming: Vec::new(),
+        next: 0,
+    }.boxed()
+}
@@ -0,0 +1,84 @@
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use futures::Future;
+use rand::{self, Rng};
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A resolver that returns the addresses in a random order.
+///
+/// The addresses returned by the inner resolver are shuffled on every
+/// resolution. With `tcp_connect_seq`, this spreads the first connection
+/// attempts of many clients across all the addresses of a host.
+#[derive(Clone)]
+pub struct ShuffleResolver<R> {
+    resolver: R,
+    rng: Option<Arc<Mutex<Box<Rng + Send>>>>,
+}
+
+impl<R> ShuffleResolver<R> where R: Resolver {
+    /// Create a new ShuffleResolver using the thread-local random number generator.
+    pub fn new(resolver: R) -> Self {
+        ShuffleResolver {
+            resolver: resolver,
+            rng: None,
+        }
+    }
+
+    /// Create a new ShuffleResolver using the given random number generator.
+    ///
+    /// A seeded generator makes the order of the addresses reproducible,
+    /// which is useful in tests.
+    pub fn with_rng<G>(resolver: R, rng: G) -> Self
+        where G: Rng + Send + 'static
+    {
+        ShuffleResolver {
+            resolver: resolver,
+            rng: Some(Arc::new(Mutex::new(Box::new(rng)))),
+        }
+    }
+}
+
+impl<R> Resolver for ShuffleResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let rng = self.rng.clone();
+
+        self.resolver.resolve(host).map(move |mut addrs| {
+            match rng {
+                Some(rng) => {
+                    let mut rng = rng.lock().unwrap();
+                    Rng::shuffle(&mut &mut **rng, &mut addrs);
+                }
+                None => rand::thread_rng().shuffle(&mut addrs),
+            }
+            addrs
+        }).boxed()
+    }
+}
+
+#[test]
+fn test_shuffle() {
+    use std::net::Ipv4Addr;
+    use futures::finished;
+    use rand::{SeedableRng, XorShiftRng};
+
+    struct Fixed;
+
+    impl Resolver for Fixed {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished((1..9).map(|i| IpAddr::V4(Ipv4Addr::new(10, 0, 0, i))).collect()).boxed()
+        }
+    }
+
+    let seed = [1, 2, 3, 4];
+    let first = ShuffleResolver::with_rng(Fixed, XorShiftRng::from_seed(seed));
+    let second = ShuffleResolver::with_rng(Fixed, XorShiftRng::from_seed(seed));
+
+    let mut addrs = first.resolve("example.com").wait().unwrap();
+    assert_eq!(addrs, second.resolve("example.com").wait().unwrap());
+
+    addrs.sort();
+    assert_eq!(addrs, Fixed.resolve("example.com").wait().unwrap());
+}
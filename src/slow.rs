@@ -0,0 +1,96 @@
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use futures::Future;
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A resolver that logs slow resolutions.
+///
+/// Every resolution made by the inner resolver is timed, and a warning
+/// with the host name and the elapsed time is logged when it takes longer
+/// than the threshold. Fast resolutions are not logged.
+#[derive(Clone)]
+pub struct SlowQueryLogResolver<R> {
+    resolver: R,
+    threshold: Duration,
+}
+
+impl<R> SlowQueryLogResolver<R> where R: Resolver {
+    /// Create a new SlowQueryLogResolver warning about resolutions slower than `threshold`.
+    pub fn new(resolver: R, threshold: Duration) -> Self {
+        SlowQueryLogResolver {
+            resolver: resolver,
+            threshold: threshold,
+        }
+    }
+}
+
+impl<R> Resolver for SlowQueryLogResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let start = Instant::now();
+        let threshold = self.threshold;
+        let host = host.to_owned();
+
+        self.resolver.resolve(&host).then(move |res| {
+            let elapsed = start.elapsed();
+            if is_slow(elapsed, threshold) {
+                warn!("{}", slow_message(&host, elapsed));
+            }
+            res
+        }).boxed()
+    }
+}
+
+// Whether a resolution that took `elapsed` is logged.
+fn is_slow(elapsed: Duration, threshold: Duration) -> bool {
+    elapsed > threshold
+}
+
+// The warning about a slow resolution of `host` that took `elapsed`.
+fn slow_message(host: &str, elapsed: Duration) -> String {
+    let millis = elapsed.subsec_nanos() / 1000000;
+    format!("slow resolution of {}: took {}.{:03}s", host, elapsed.as_secs(), millis)
+}
+
+#[test]
+fn test_is_slow() {
+    let threshold = Duration::from_millis(100);
+    assert!(is_slow(Duration::from_millis(101), threshold));
+    assert!(!is_slow(Duration::from_millis(100), threshold));
+    assert!(!is_slow(Duration::from_millis(3), threshold));
+}
+
+#[test]
+fn test_slow_message() {
+    assert_eq!(slow_message("example.com", Duration::from_millis(1042)), "slow resolution of example.com: took 1.042s");
+}
+
+#[test]
+fn test_slow_query_log() {
+    use std::io;
+    use std::net::Ipv4Addr;
+    use std::thread;
+    use futures::{failed, finished};
+
+    struct Slow;
+
+    impl Resolver for Slow {
+        fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+            thread::sleep(Duration::from_millis(5));
+            if host == "example.com" {
+                finished(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]).boxed()
+            } else {
+                failed(io::Error::new(io::ErrorKind::NotFound, "not found")).boxed()
+            }
+        }
+    }
+
+    // The results are passed through, whether the resolution is slow or not.
+    for &threshold in &[Duration::from_secs(0), Duration::from_secs(60)] {
+        let resolver = SlowQueryLogResolver::new(Slow, threshold);
+        assert_eq!(resolver.resolve("example.com").wait().unwrap(), vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+        assert_eq!(resolver.resolve("other").wait().unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
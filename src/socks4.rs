@@ -0,0 +1,128 @@
+use std::io;
+use std::net::SocketAddr;
+
+use futures::{failed, Future};
+use tokio_core::TcpStream;
+use tokio_core::io::{flush, read_exact, write_all, IoFuture};
+
+use super::{Endpoint, ToEndpoint};
+
+/// Open a tunnel through a SOCKS4 proxy on a stream connected to it.
+///
+/// A `CONNECT` request for `target` is sent to the proxy, with `userid`
+/// if given. A socket address is sent as is, like in SOCKS4, and a host
+/// name is sent for the proxy to resolve, like in SOCKS4a. IPv6 addresses
+/// aren't supported by the protocol and fail with an error of kind
+/// `InvalidInput`. The future resolves into the stream once the proxy
+/// granted the request. A rejection is an error, with the reason given
+/// by the proxy in its message.
+pub fn socks4_connect(stream: TcpStream, target: &Endpoint, userid: Option<&str>) -> IoFuture<TcpStream> {
+    let request = match connect_request(target, userid) {
+        Ok(request) => request,
+        Err(e) => return failed(e).boxed(),
+    };
+
+    write_all(stream, request).and_then(|(stream, _)| flush(stream)).and_then(|stream| {
+        read_exact(stream, [0u8; 8])
+    }).and_then(|(stream, reply)| {
+        try!(check_reply(&reply));
+        Ok(stream)
+    }).boxed()
+}
+
+// Chains the tunnel request to the connection to the proxy.
+pub fn via_socks4<'a, T>(connect: IoFuture<TcpStream>, target: T, userid: Option<&str>) -> IoFuture<TcpStream>
+    where T: ToEndpoint<'a>
+{
+    let target = match target.to_endpoint() {
+        Ok(target) => target.to_buf(),
+        Err(e) => return failed(e).boxed(),
+    };
+    let userid = userid.map(|userid| userid.to_owned());
+
+    connect.and_then(move |stream| {
+        debug!("opening a tunnel to {} through the SOCKS4 proxy", target);
+        socks4_connect(stream, &target.as_endpoint(), userid.as_ref().map(|userid| &userid[..]))
+    }).boxed()
+}
+
+fn push_string(request: &mut Vec<u8>, s: &str, what: &str) -> io::Result<()> {
+    if s.as_bytes().contains(&0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("the {} contains a nul byte", what)));
+    }
+    request.extend_from_slice(s.as_bytes());
+    request.push(0);
+    Ok(())
+}
+
+fn connect_request(target: &Endpoint, userid: Option<&str>) -> io::Result<Vec<u8>> {
+    let port = target.port();
+    let mut request = vec![4, 1, (port >> 8) as u8, port as u8];
+
+    let host = match *target {
+        Endpoint::SocketAddr(SocketAddr::V4(addr)) => {
+            request.extend_from_slice(&addr.ip().octets());
+            None
+        }
+        Endpoint::SocketAddr(SocketAddr::V6(_)) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "SOCKS4 does not support IPv6 destinations"));
+        }
+        Endpoint::Host(host, _) => {
+            // An invalid address of the form 0.0.0.x asks the proxy to resolve the host name.
+            request.extend_from_slice(&[0, 0, 0, 1]);
+            Some(host)
+        }
+    };
+
+    try!(push_string(&mut request, userid.unwrap_or(""), "user id"));
+    if let Some(host) = host {
+        try!(push_string(&mut request, host, "host name"));
+    }
+
+    Ok(request)
+}
+
+fn check_reply(reply: &[u8; 8]) -> io::Result<()> {
+    if reply[0] != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed reply from the SOCKS4 proxy"));
+    }
+
+    let reason = match reply[1] {
+        // The request was granted.
+        90 => return Ok(()),
+        91 => "request rejected or failed",
+        92 => "the proxy could not reach the identd of the client",
+        93 => "the identd of the client reported a different user id",
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed reply from the SOCKS4 proxy")),
+    };
+
+    Err(io::Error::new(io::ErrorKind::Other, format!("the proxy refused the tunnel: {}", reason)))
+}
+
+#[test]
+fn test_connect_request() {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    let target = Endpoint::SocketAddr(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 80));
+    assert_eq!(connect_request(&target, Some("bob")).unwrap(),
+               vec![4, 1, 0, 80, 10, 0, 0, 1, b'b', b'o', b'b', 0]);
+
+    let target = "example.com:443".to_endpoint().unwrap();
+    let mut expected = vec![4, 1, 1, 187, 0, 0, 0, 1, 0];
+    expected.extend_from_slice(b"example.com\0");
+    assert_eq!(connect_request(&target, None).unwrap(), expected);
+
+    let target = "[::1]:80".to_endpoint().unwrap();
+    assert_eq!(connect_request(&target, None).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_check_reply() {
+    assert!(check_reply(&[0, 90, 0, 0, 0, 0, 0, 0]).is_ok());
+
+    let e = check_reply(&[0, 91, 0, 0, 0, 0, 0, 0]).unwrap_err();
+    assert_eq!(e.to_string(), "the proxy refused the tunnel: request rejected or failed");
+
+    let e = check_reply(&[b'H', b'T', b'T', b'P', b'/', b'1', b'.', b'1']).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::time::{Duration, Instant};
+
+struct Entry {
+    successes: f64,
+    failures: f64,
+    updated: Instant,
+    // The tick of the last use of the entry, the oldest one is evicted first.
+    used: usize,
+}
+
+impl Entry {
+    // Decays the counts so that they lose half of their weight every `half_life`.
+    fn decay(&mut self, now: Instant, half_life: Duration) {
+        let factor = 0.5f64.powf(secs(now.duration_since(self.updated)) / secs(half_life));
+        self.successes *= factor;
+        self.failures *= factor;
+        self.updated = now;
+    }
+}
+
+fn secs(dur: Duration) -> f64 {
+    dur.as_secs() as f64 + dur.subsec_nanos() as f64 / 1e9
+}
+
+/// Connection statistics of addresses.
+///
+/// The number of successful and failed connection attempts is recorded for
+/// every address. Old outcomes lose half of their weight every `half_life`,
+/// so an address that recovers is eventually tried again.
+///
+/// By default, the statistics of every address are kept. Use `max_entries`
+/// to bound their memory, the statistics of the least recently used
+/// address are then dropped first, as if it had never been tried.
+///
+/// The statistics can be cloned cheaply, the clones share the same data.
+/// Give them to a `Connector` with `Connector::stats` to try the most
+/// reliable addresses first in `tcp_connect_seq`.
+#[derive(Clone)]
+pub struct ConnectStats {
+    entries: Arc<Mutex<HashMap<IpAddr, Entry>>>,
+    half_life: Duration,
+    max_entries: Option<usize>,
+    clock: Arc<AtomicUsize>,
+}
+
+impl ConnectStats {
+    /// Create new, empty statistics.
+    pub fn new(half_life: Duration) -> Self {
+        ConnectStats {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            half_life: half_life,
+            max_entries: None,
+            clock: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Set the maximum number of addresses to keep statistics for.
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = Some(max);
+        self
+    }
+
+    /// Record the outcome of a connection attempt to `addr`.
+    pub fn record(&self, addr: IpAddr, success: bool) {
+        let now = Instant::now();
+        let used = self.clock.fetch_add(1, AtomicOrdering::SeqCst);
+        let mut entries = self.entries.lock().unwrap();
+
+        {
+            let entry = entries.entry(addr).or_insert(Entry {
+                successes: 0.0,
+                failures: 0.0,
+                updated: now,
+                used: used,
+            });
+
+            entry.decay(now, self.half_life);
+            entry.used = used;
+            if success {
+                entry.successes += 1.0;
+            } else {
+                entry.failures += 1.0;
+            }
+        }
+
+        if let Some(max) = self.max_entries {
+            while entries.len() > max {
+                let oldest = match entries.iter().min_by_key(|&(_, entry)| entry.used) {
+                    Some((&oldest, _)) => oldest,
+                    None => break,
+                };
+                debug!("dropping the connection statistics of {}", oldest);
+                entries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Get the estimated probability that a connection attempt to `addr` succeeds.
+    ///
+    /// An address without statistics has a score of `0.5`.
+    pub fn score(&self, addr: IpAddr) -> f64 {
+        let now = Instant::now();
+        let used = self.clock.fetch_add(1, AtomicOrdering::SeqCst);
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get_mut(&addr) {
+            Some(entry) => {
+                entry.decay(now, self.half_life);
+                entry.used = used;
+                (entry.successes + 1.0) / (entry.successes + entry.failures + 2.0)
+            }
+            None => 0.5,
+        }
+    }
+
+    /// Sort the addresses from the most reliable to the least reliable.
+    ///
+    /// Addresses with the same score keep their order.
+    pub fn sort(&self, addrs: &mut Vec<IpAddr>) {
+        let mut scored: Vec<(f64, IpAddr)> = addrs.iter().map(|&addr| (self.score(addr), addr)).collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+
+        addrs.clear();
+        addrs.extend(scored.into_iter().map(|(_, addr)| addr));
+    }
+}
+
+#[test]
+fn test_sort() {
+    use std::net::Ipv4Addr;
+
+    let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    let c = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+    let d = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 4));
+
+    let stats = ConnectStats::new(Duration::from_secs(60));
+    stats.record(a, false);
+    stats.record(a, false);
+    stats.record(b, true);
+
+    let mut addrs = vec![a, c, b, d];
+    stats.sort(&mut addrs);
+    assert_eq!(addrs, vec![b, c, d, a]);
+}
+
+#[test]
+fn test_max_entries() {
+    use std::net::Ipv4Addr;
+
+    let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    let c = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+
+    let stats = ConnectStats::new(Duration::from_secs(60)).max_entries(2);
+    stats.record(a, false);
+    stats.record(b, false);
+    assert!(stats.score(a) < 0.5);
+    stats.record(c, false);
+
+    assert!(stats.score(a) < 0.5);
+    assert_eq!(stats.score(b), 0.5);
+    assert!(stats.score(c) < 0.5);
+}
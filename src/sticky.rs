@@ -0,0 +1,102 @@
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+/// The addresses that host names were last successfully connected to.
+///
+/// At most `capacity` host names are remembered, the least recently used
+/// one is forgotten first. The address of a host name is forgotten when
+/// a connection attempt to it fails.
+///
+/// The addresses can be cloned cheaply, the clones share the same data.
+/// Give them to a `Connector` with `Connector::sticky` to try the address
+/// that worked last time first.
+#[derive(Clone)]
+pub struct StickyAddresses {
+    // The most recently used host name is last.
+    entries: Arc<Mutex<Vec<(String, IpAddr)>>>,
+    capacity: usize,
+}
+
+impl StickyAddresses {
+    /// Create a new, empty memory of at most `capacity` host names.
+    pub fn new(capacity: usize) -> Self {
+        StickyAddresses {
+            entries: Arc::new(Mutex::new(Vec::new())),
+            capacity: capacity,
+        }
+    }
+
+    /// Remember that a connection to `addr` succeeded for `host`.
+    pub fn record_success(&self, host: &str, addr: IpAddr) {
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.retain(|&(ref known, _)| known != host);
+        entries.push((host.to_owned(), addr));
+
+        if entries.len() > self.capacity {
+            let excess = entries.len() - self.capacity;
+            entries.drain(..excess);
+        }
+    }
+
+    /// Forget the address of `host` if it is `addr`, because a connection to it failed.
+    pub fn record_failure(&self, host: &str, addr: IpAddr) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|&(ref known, known_addr)| known != host || known_addr != addr);
+    }
+
+    /// Get the address remembered for `host`.
+    pub fn get(&self, host: &str) -> Option<IpAddr> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let pos = match entries.iter().position(|&(ref known, _)| known == host) {
+            Some(pos) => pos,
+            None => return None,
+        };
+
+        let entry = entries.remove(pos);
+        let addr = entry.1;
+        entries.push(entry);
+        Some(addr)
+    }
+
+    /// Move the address remembered for `host` to the front of the addresses.
+    ///
+    /// The other addresses keep their order. Nothing is changed if no
+    /// address is remembered for `host` or if it isn't one of the addresses.
+    pub fn promote(&self, host: &str, addrs: &mut Vec<IpAddr>) {
+        if let Some(addr) = self.get(host) {
+            if let Some(pos) = addrs.iter().position(|&a| a == addr) {
+                let addr = addrs.remove(pos);
+                addrs.insert(0, addr);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sticky() {
+    use std::net::Ipv4Addr;
+
+    let a = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let b = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+    let c = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3));
+
+    let sticky = StickyAddresses::new(2);
+    sticky.record_success("one.example.com", c);
+    sticky.record_success("two.example.com", b);
+
+    let mut addrs = vec![a, b, c];
+    sticky.promote("one.example.com", &mut addrs);
+    assert_eq!(addrs, vec![c, a, b]);
+
+    // two.example.com is now the least recently used.
+    sticky.record_success("three.example.com", a);
+    assert_eq!(sticky.get("two.example.com"), None);
+    assert_eq!(sticky.get("one.example.com"), Some(c));
+
+    sticky.record_failure("one.example.com", a);
+    assert_eq!(sticky.get("one.example.com"), Some(c));
+    sticky.record_failure("one.example.com", c);
+    assert_eq!(sticky.get("one.example.com"), None);
+}
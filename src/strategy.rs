@@ -0,0 +1,120 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures::{failed, Future};
+use tokio_core::{LoopHandle, TcpStream};
+use tokio_core::io::IoFuture;
+
+use super::common::{chain_connects, par_connects, validate_endpoint};
+use super::hedged::connect_hedged;
+use super::{Resolver, SocketOptions, ToEndpoint};
+
+/// The ways to connect to one of several addresses.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Strategy {
+    /// Try every address at the same time, like `DnsSupport::tcp_connect_par`.
+    Parallel,
+    /// Try the addresses one after the other, like `DnsSupport::tcp_connect_seq`.
+    Sequential,
+    /// Try the next address whenever the delay elapses without a connection,
+    /// like `DnsSupport::tcp_connect_hedged`.
+    Hedged(Duration),
+}
+
+/// Create a new TcpStream connected to one of the given addresses.
+///
+/// The addresses are tried according to `strategy`, and the first
+/// connection established is returned, once `options` are set on it.
+/// Together with `DnsSupport::validate_endpoint`, this lets an endpoint
+/// be resolved once and connected to many times without being resolved
+/// again, with the same snapshot of addresses given to every strategy.
+/// The addresses of `Resolver::resolve` can be given too, once paired
+/// with a port. If `options` can't be set on this platform, it fails
+/// before anything is connected to.
+pub fn connect_to_addrs(handle: LoopHandle, addrs: Vec<SocketAddr>, strategy: Strategy,
+                        options: Option<SocketOptions>)
+                        -> IoFuture<TcpStream> {
+    if let Some(Err(e)) = options.map(|options| options.check()) {
+        return failed(e).boxed();
+    }
+
+    if addrs.is_empty() {
+        return failed(io::Error::new(io::ErrorKind::Other, "no addresses to connect to")).boxed();
+    }
+
+    let ep = "the given addresses";
+
+    let fut = match strategy {
+        Strategy::Parallel => par_connects(handle, &ep, addrs),
+        Strategy::Sequential => chain_connects(handle, &ep, addrs, None),
+        Strategy::Hedged(delay) => connect_hedged(handle, addrs, delay),
+    };
+
+    match options {
+        Some(options) => fut.and_then(move |stream| options.apply(&stream).map(|()| stream)).boxed(),
+        None => fut,
+    }
+}
+
+// Resolves the endpoint once, and connects to the addresses with the strategy.
+pub fn tcp_connect_strategy<'a, R, T>(handle: LoopHandle, resolver: R, ep: T, strategy: Strategy) -> IoFuture<TcpStream>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    validate_endpoint(resolver, ep).and_then(move |addrs| connect_to_addrs(handle, addrs, strategy, None)).boxed()
+}
+
+#[test]
+fn test_no_addresses() {
+    use tokio_core::Loop;
+
+    let lp = Loop::new().unwrap();
+    for &strategy in &[Strategy::Parallel, Strategy::Sequential, Strategy::Hedged(Duration::from_millis(10))] {
+        match connect_to_addrs(lp.handle(), vec![], strategy, None).wait() {
+            Ok(_) => panic!("connected without addresses"),
+            Err(e) => assert_eq!(e.to_string(), "no addresses to connect to"),
+        }
+    }
+}
+
+#[test]
+fn test_connect_strategy_resolves_once() {
+    use std::net::IpAddr;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use futures::finished;
+    use tokio_core::Loop;
+
+    struct Nothing(Arc<AtomicUsize>);
+
+    impl Resolver for Nothing {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            finished(vec![]).boxed()
+        }
+    }
+
+    let lp = Loop::new().unwrap();
+    let count = Arc::new(AtomicUsize::new(0));
+    let fut = tcp_connect_strategy(lp.handle(), Nothing(count.clone()), "example.com:80", Strategy::Parallel);
+    assert!(fut.wait().is_err());
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_connect_with_options() {
+    use std::net::TcpListener;
+    use tokio_core::Loop;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut lp = Loop::new().unwrap();
+    let options = SocketOptions::new().nodelay(true);
+    for &strategy in &[Strategy::Parallel, Strategy::Sequential] {
+        let connect = connect_to_addrs(lp.handle(), vec![addr], strategy, Some(options));
+        let stream = lp.run(connect).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+    }
+}
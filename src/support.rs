@@ -1,14 +1,34 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
 use tokio_core::{LoopHandle, TcpStream, TcpListener, UdpSocket};
-use tokio_core::io::IoFuture;
+use tokio_core::io::{IoFuture, IoStream};
 
-use super::common::{tcp_connect_seq, tcp_connect_par, tcp_listen_seq, udp_bind_seq};
-use super::{CpuPoolResolver, ToEndpoint};
+use super::candidates::tcp_connect_candidates;
+use super::common::{tcp_connect_seq, tcp_connect_seq_budget, tcp_connect_failover, tcp_connect_par, tcp_connect_par_n, tcp_connect_checked, tcp_listen_seq,
+                     tcp_listen_ephemeral, udp_bind_seq, validate_endpoint, with_deadline};
+use super::hedged::tcp_connect_hedged;
+use super::http_proxy::via_http_proxy;
+use super::many::tcp_connect_many;
+use super::proxy_protocol::with_proxy_header;
+use super::serve::tcp_serve;
+use super::socks4::via_socks4;
+use super::strategy::tcp_connect_strategy;
+use super::timings::tcp_connect_timed;
+use super::watch::watch_resolution;
+use super::{ConnectTimings, CpuPoolResolver, ProxyHeader, Strategy, ToEndpoint};
 
 lazy_static! {
     static ref POOL: CpuPoolResolver = CpuPoolResolver::new(5);
 }
 
 /// An extension trait to add name resolution to objects.
+///
+/// The connections are made with `LoopHandle::tcp_connect`, which resolves
+/// once the socket is reported writable and it holds no pending error, so
+/// a TcpStream returned has completed its handshake and can be written to
+/// right away. No extra wait for writability is needed.
 pub trait DnsSupport {
     /// Create a new TcpStream connected to the specified endpoint.
     ///
@@ -17,13 +37,153 @@ pub trait DnsSupport {
     fn tcp_connect_par<'a, T>(&self, ep: T) -> IoFuture<TcpStream>
         where T: ToEndpoint<'a>;
 
+    /// Create a new TcpStream connected to the specified endpoint and checked by `check`.
+    ///
+    /// If the endpoint is a hostname, it will be resolved and every
+    /// address returned will be tried in parallel. Every connection
+    /// established is given to `check`, which hands it back along with
+    /// whether it passed an application-level check, such as reading a
+    /// banner. The first connection that passes the check is returned,
+    /// the other ones are closed.
+    fn tcp_connect_checked<'a, T, F>(&self, ep: T, check: F) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>,
+              F: Fn(TcpStream) -> IoFuture<(TcpStream, bool)> + Send + Sync + 'static;
+
+    /// Create up to `n` TcpStreams connected to the specified endpoint.
+    ///
+    /// If the endpoint is a hostname, it will be resolved and every
+    /// address returned will be tried in parallel. The future resolves
+    /// once `n` connections succeeded, and the remaining attempts are
+    /// cancelled. If fewer than `n` attempts succeed, the successful
+    /// connections are returned along with the errors of the failed ones.
+    /// It fails if none of the attempts succeed.
+    fn tcp_connect_par_n<'a, T>(&self, ep: T, n: usize) -> IoFuture<(Vec<TcpStream>, Vec<io::Error>)>
+        where T: ToEndpoint<'a>;
+
     /// Create a new TcpStream connected to the specified endpoint.
     ///
     /// If the endpoint is a hostname, it will be resolved and every
-    /// address returned will be tried one after the other.
+    /// address returned will be tried one after the other. The host name
+    /// is resolved once: all of the addresses tried come from that single
+    /// resolution, even if the resolver returns other addresses meanwhile.
     fn tcp_connect_seq<'a, T>(&self, ep: T) -> IoFuture<TcpStream>
         where T: ToEndpoint<'a>;
 
+    /// Create a new TcpStream connected to the specified endpoint, hedging the attempts.
+    ///
+    /// If the endpoint is a hostname, it will be resolved and the addresses
+    /// will be tried in order, like in `tcp_connect_seq`. However, when an
+    /// attempt neither succeeds nor fails within `delay`, the next address
+    /// is tried while the previous attempts keep running. The first
+    /// connection established is returned, the other ones are closed.
+    fn tcp_connect_hedged<'a, T>(&self, ep: T, delay: Duration) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>;
+
+    /// Create a new TcpStream connected to the specified endpoint with a strategy.
+    ///
+    /// If the endpoint is a hostname, it will be resolved once, and the
+    /// addresses returned will be tried according to `strategy`. This is
+    /// `validate_endpoint` followed by `connect_to_addrs`: call these
+    /// directly to connect to the same snapshot of addresses several times.
+    fn tcp_connect_strategy<'a, T>(&self, ep: T, strategy: Strategy) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>;
+
+    /// Create a new TcpStream connected to the specified endpoint, and measure it.
+    ///
+    /// This behaves like `tcp_connect_seq`, and returns the stream along
+    /// with the time taken to resolve the endpoint, the time taken by the
+    /// connection attempts and the total time.
+    fn tcp_connect_timed<'a, T>(&self, ep: T) -> IoFuture<(TcpStream, ConnectTimings)>
+        where T: ToEndpoint<'a>;
+
+    /// Create a new TcpStream connected to the first of the endpoints that accepts a connection.
+    ///
+    /// The endpoints are tried in order. Each one is connected to like in
+    /// `tcp_connect_seq`, so all of its addresses are tried before moving
+    /// on to the next endpoint. If every endpoint fails, the error lists
+    /// the failure of each one. A comma-separated list of endpoints can be
+    /// given as `list.split(',')`.
+    fn tcp_connect_failover<'a, I, T>(&self, eps: I) -> IoFuture<TcpStream>
+        where I: IntoIterator<Item = T>,
+              T: ToEndpoint<'a>;
+
+    /// Create new TcpStreams connected to each of the specified endpoints.
+    ///
+    /// Every endpoint is connected to like in `tcp_connect_seq`, with at
+    /// most `limit` of them at the same time. Unlike `tcp_connect_failover`,
+    /// all of the endpoints are connected to. The future resolves into a
+    /// result per endpoint, in the order of the endpoints, once every
+    /// connect completed.
+    fn tcp_connect_many<'a, I, T>(&self, eps: I, limit: usize) -> IoFuture<Vec<io::Result<TcpStream>>>
+        where I: IntoIterator<Item = T>,
+              T: ToEndpoint<'a>;
+
+    /// Create a new TcpStream connected to the specified endpoint before the deadline.
+    ///
+    /// This behaves like `tcp_connect_par`, but fails with an error of kind
+    /// `TimedOut` if the connection isn't established when the deadline is
+    /// reached. It fails immediately if the deadline has already passed.
+    fn tcp_connect_par_deadline<'a, T>(&self, ep: T, deadline: Instant) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>;
+
+    /// Create a new TcpStream connected to the specified endpoint before the deadline.
+    ///
+    /// This behaves like `tcp_connect_seq`, but fails with an error of kind
+    /// `TimedOut` if the connection isn't established when the deadline is
+    /// reached. It fails immediately if the deadline has already passed.
+    fn tcp_connect_seq_deadline<'a, T>(&self, ep: T, deadline: Instant) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>;
+
+    /// Create a new TcpStream connected to the specified endpoint, with a time budget per address.
+    ///
+    /// This behaves like `tcp_connect_seq`, but fails with an error of kind
+    /// `TimedOut` if the connection isn't established in time. The time
+    /// allowed is `budget` for each address the host resolves to, capped at
+    /// `max`, so each sequential attempt gets a fair share of it. It starts
+    /// once the host is resolved.
+    fn tcp_connect_seq_budget<'a, T>(&self, ep: T, budget: Duration, max: Duration) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>;
+
+    /// Create a new TcpStream tunneled to `target` through an HTTP proxy.
+    ///
+    /// The proxy endpoint is connected to like in `tcp_connect_seq`, then
+    /// a tunnel to `target` is opened with a `CONNECT` request, see
+    /// `http_connect`. The target isn't resolved locally, the proxy does
+    /// it. The stream is ready for the protocol of the target, such as TLS.
+    fn tcp_connect_via_http_proxy<'a, 'b, P, T>(&self, proxy: P, target: T, authorization: Option<&str>)
+                                                -> IoFuture<TcpStream>
+        where P: ToEndpoint<'a>,
+              T: ToEndpoint<'b>;
+
+    /// Create a new TcpStream tunneled to `target` through a SOCKS4 proxy.
+    ///
+    /// The proxy endpoint is connected to like in `tcp_connect_seq`, then
+    /// a tunnel to `target` is opened with `userid`, see `socks4_connect`.
+    /// A host name target isn't resolved locally, the proxy does it, like
+    /// in SOCKS4a. IPv6 targets aren't supported by the protocol.
+    fn tcp_connect_via_socks4<'a, 'b, P, T>(&self, proxy: P, target: T, userid: Option<&str>) -> IoFuture<TcpStream>
+        where P: ToEndpoint<'a>,
+              T: ToEndpoint<'b>;
+
+    /// Create a new TcpStream which starts with a PROXY protocol header.
+    ///
+    /// The endpoint is connected to like in `tcp_connect_seq`, then `header`
+    /// is written and flushed with `write_proxy_header`, before the future
+    /// resolves. Nothing else can be written on the stream before it.
+    fn tcp_connect_with_proxy_header<'a, T>(&self, ep: T, header: &ProxyHeader) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>;
+
+    /// Create a stream of connection attempts to the specified endpoint.
+    ///
+    /// If the endpoint is a hostname, it will be resolved. Each time the
+    /// stream is polled for a new item, a connection to the next address
+    /// is attempted. The stream yields every address along with the result
+    /// of the attempt, so the caller can pick connections by its own criteria.
+    /// The addresses are tried as soon as the resolver yields them, see
+    /// `Resolver::resolve_stream`. The stream fails if the resolution fails.
+    fn tcp_connect_candidates<'a, T>(&self, ep: T) -> IoStream<(SocketAddr, io::Result<TcpStream>)>
+        where T: ToEndpoint<'a>;
+
     /// Create a new TcpListener bound to the specified endpoint.
     ///
     /// If the endpoint is a hostname, it will be resolved and every
@@ -31,12 +191,46 @@ pub trait DnsSupport {
     fn tcp_listen_seq<'a, T>(&self, ep: T) -> IoFuture<TcpListener>
         where T: ToEndpoint<'a>;
 
+    /// Create a new TcpListener bound to a port chosen by the OS.
+    ///
+    /// The host is resolved like in `tcp_listen_seq` and the listener is
+    /// bound to port 0 on the first address that can be bound. The listener
+    /// is returned along with the address it is actually bound to, which
+    /// holds the assigned port.
+    fn tcp_listen_ephemeral(&self, host: &str) -> IoFuture<(TcpListener, SocketAddr)>;
+
+    /// Create a stream of the connections accepted on the specified endpoint.
+    ///
+    /// If the endpoint is a hostname, it will be resolved and a listener
+    /// will be bound to every address returned. The incoming connections
+    /// of all the listeners are merged into the stream. Addresses that
+    /// can't be bound are skipped, the stream fails if none can be bound.
+    fn tcp_serve<'a, T>(&self, ep: T) -> IoStream<(TcpStream, SocketAddr)>
+        where T: ToEndpoint<'a>;
+
     /// Create a new UdpSocket bound to the specified endpoint.
     ///
     /// If the endpoint is a hostname, it will be resolved and every
     /// address returned will be tried one after the other.
     fn udp_bind_seq<'a, T>(&self, ep: T) -> IoFuture<UdpSocket>
         where T: ToEndpoint<'a>;
+
+    /// Check that the specified endpoint is valid, without connecting to it.
+    ///
+    /// The endpoint is parsed and resolved like in the connect methods, and
+    /// the socket addresses that would be tried are returned. It fails if
+    /// the endpoint can't be parsed or resolved, or if it designates no
+    /// address. No connection is attempted.
+    fn validate_endpoint<'a, T>(&self, ep: T) -> IoFuture<Vec<SocketAddr>>
+        where T: ToEndpoint<'a>;
+
+    /// Create a stream of the addresses of the specified host.
+    ///
+    /// The host is resolved every `interval`, and its addresses are yielded
+    /// whenever they differ from the previous ones, regardless of their order.
+    /// The first successful resolution is always yielded. Resolution errors
+    /// are logged and do not terminate the stream.
+    fn watch_resolution(&self, host: &str, interval: Duration) -> IoStream<Vec<IpAddr>>;
 }
 
 impl DnsSupport for LoopHandle {
@@ -46,21 +240,133 @@ impl DnsSupport for LoopHandle {
         tcp_connect_par(self.clone(), POOL.clone(), ep)
     }
 
+    fn tcp_connect_checked<'a, T, F>(&self, ep: T, check: F) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>,
+              F: Fn(TcpStream) -> IoFuture<(TcpStream, bool)> + Send + Sync + 'static
+    {
+        tcp_connect_checked(self.clone(), POOL.clone(), ep, check)
+    }
+
+    fn tcp_connect_par_n<'a, T>(&self, ep: T, n: usize) -> IoFuture<(Vec<TcpStream>, Vec<io::Error>)>
+        where T: ToEndpoint<'a>
+    {
+        tcp_connect_par_n(self.clone(), POOL.clone(), ep, n)
+    }
+
     fn tcp_connect_seq<'a, T>(&self, ep: T) -> IoFuture<TcpStream>
         where T: ToEndpoint<'a>
     {
         tcp_connect_seq(self.clone(), POOL.clone(), ep)
     }
 
+    fn tcp_connect_hedged<'a, T>(&self, ep: T, delay: Duration) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        tcp_connect_hedged(self.clone(), POOL.clone(), ep, delay)
+    }
+
+    fn tcp_connect_strategy<'a, T>(&self, ep: T, strategy: Strategy) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        tcp_connect_strategy(self.clone(), POOL.clone(), ep, strategy)
+    }
+
+    fn tcp_connect_timed<'a, T>(&self, ep: T) -> IoFuture<(TcpStream, ConnectTimings)>
+        where T: ToEndpoint<'a>
+    {
+        tcp_connect_timed(self.clone(), POOL.clone(), ep, None)
+    }
+
+    fn tcp_connect_failover<'a, I, T>(&self, eps: I) -> IoFuture<TcpStream>
+        where I: IntoIterator<Item = T>,
+              T: ToEndpoint<'a>
+    {
+        let handle = self.clone();
+        tcp_connect_failover(eps, move |ep| handle.tcp_connect_seq(&ep))
+    }
+
+    fn tcp_connect_many<'a, I, T>(&self, eps: I, limit: usize) -> IoFuture<Vec<io::Result<TcpStream>>>
+        where I: IntoIterator<Item = T>,
+              T: ToEndpoint<'a>
+    {
+        let handle = self.clone();
+        tcp_connect_many(eps, limit, move |ep| handle.tcp_connect_seq(&ep))
+    }
+
+    fn tcp_connect_par_deadline<'a, T>(&self, ep: T, deadline: Instant) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        with_deadline(self.clone(), deadline, || self.tcp_connect_par(ep))
+    }
+
+    fn tcp_connect_seq_deadline<'a, T>(&self, ep: T, deadline: Instant) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        with_deadline(self.clone(), deadline, || self.tcp_connect_seq(ep))
+    }
+
+    fn tcp_connect_seq_budget<'a, T>(&self, ep: T, budget: Duration, max: Duration) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        tcp_connect_seq_budget(self.clone(), POOL.clone(), ep, budget, max, None)
+    }
+
+    fn tcp_connect_via_http_proxy<'a, 'b, P, T>(&self, proxy: P, target: T, authorization: Option<&str>)
+                                                -> IoFuture<TcpStream>
+        where P: ToEndpoint<'a>,
+              T: ToEndpoint<'b>
+    {
+        via_http_proxy(self.tcp_connect_seq(proxy), target, authorization)
+    }
+
+    fn tcp_connect_via_socks4<'a, 'b, P, T>(&self, proxy: P, target: T, userid: Option<&str>) -> IoFuture<TcpStream>
+        where P: ToEndpoint<'a>,
+              T: ToEndpoint<'b>
+    {
+        via_socks4(self.tcp_connect_seq(proxy), target, userid)
+    }
+
+    fn tcp_connect_with_proxy_header<'a, T>(&self, ep: T, header: &ProxyHeader) -> IoFuture<TcpStream>
+        where T: ToEndpoint<'a>
+    {
+        with_proxy_header(self.tcp_connect_seq(ep), header)
+    }
+
+    fn tcp_connect_candidates<'a, T>(&self, ep: T) -> IoStream<(SocketAddr, io::Result<TcpStream>)>
+        where T: ToEndpoint<'a>
+    {
+        tcp_connect_candidates(self.clone(), POOL.clone(), ep)
+    }
+
     fn tcp_listen_seq<'a, T>(&self, ep: T) -> IoFuture<TcpListener>
         where T: ToEndpoint<'a>
     {
         tcp_listen_seq(self.clone(), POOL.clone(), ep)
     }
 
+    fn tcp_listen_ephemeral(&self, host: &str) -> IoFuture<(TcpListener, SocketAddr)> {
+        tcp_listen_ephemeral(self.clone(), POOL.clone(), (host, 0))
+    }
+
+    fn tcp_serve<'a, T>(&self, ep: T) -> IoStream<(TcpStream, SocketAddr)>
+        where T: ToEndpoint<'a>
+    {
+        tcp_serve(self.clone(), POOL.clone(), ep)
+    }
+
     fn udp_bind_seq<'a, T>(&self, ep: T) -> IoFuture<UdpSocket>
         where T: ToEndpoint<'a>
     {
         udp_bind_seq(self.clone(), POOL.clone(), ep)
     }
+
+    fn validate_endpoint<'a, T>(&self, ep: T) -> IoFuture<Vec<SocketAddr>>
+        where T: ToEndpoint<'a>
+    {
+        validate_endpoint(POOL.clone(), ep)
+    }
+
+    fn watch_resolution(&self, host: &str, interval: Duration) -> IoStream<Vec<IpAddr>> {
+        watch_resolution(self.clone(), POOL.clone(), host, interval)
+    }
 }
@@ -0,0 +1,60 @@
+use std::net::{IpAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+use futures::Future;
+use futures_cpupool::CpuPool;
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A resolver which adapts blocking `ToSocketAddrs` implementations.
+///
+/// For every host name, the function gives an object implementing
+/// `ToSocketAddrs`, which is then resolved inside a thread of a pool,
+/// like in the `CpuPoolResolver`. The ports of the socket addresses
+/// are discarded. This lets existing blocking resolution code be used
+/// with the connect helpers.
+#[derive(Clone)]
+pub struct SyncResolver<F> {
+    pool: CpuPool,
+    func: Arc<F>,
+}
+
+impl<F, T> SyncResolver<F>
+    where F: Fn(&str) -> T + Send + Sync + 'static,
+          T: ToSocketAddrs
+{
+    /// Create a new SyncResolver with the given number of threads.
+    pub fn new(num_threads: usize, func: F) -> Self {
+        SyncResolver {
+            pool: CpuPool::new(num_threads),
+            func: Arc::new(func),
+        }
+    }
+}
+
+impl<F, T> Resolver for SyncResolver<F>
+    where F: Fn(&str) -> T + Send + Sync + 'static,
+          T: ToSocketAddrs
+{
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let host = host.to_owned();
+        let func = self.func.clone();
+
+        self.pool.spawn_fn(move || {
+            match func(&host).to_socket_addrs() {
+                Ok(it) => Ok(it.map(|s| s.ip()).collect()),
+                Err(e) => Err(e),
+            }
+        }).boxed()
+    }
+}
+
+#[test]
+fn test_sync_resolver() {
+    use std::net::Ipv4Addr;
+
+    let resolver = SyncResolver::new(1, |host: &str| format!("{}:80", host));
+    let addrs = resolver.resolve("127.0.0.1").wait().unwrap();
+    assert_eq!(addrs, vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))]);
+}
@@ -0,0 +1,61 @@
+use std::io;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+use libc;
+use tokio_core::TcpStream;
+
+// Sets one of SO_RCVTIMEO and SO_SNDTIMEO, None disables the timeout.
+fn set_timeout(stream: &TcpStream, opt: libc::c_int, dur: Option<Duration>) -> io::Result<()> {
+    let tv = match dur {
+        Some(dur) => {
+            if dur == Duration::new(0, 0) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot set a zero duration timeout"));
+            }
+
+            let mut tv = libc::timeval {
+                tv_sec: dur.as_secs() as libc::time_t,
+                tv_usec: (dur.subsec_nanos() / 1000) as libc::suseconds_t,
+            };
+            // A zero timeval would disable the timeout.
+            if tv.tv_sec == 0 && tv.tv_usec == 0 {
+                tv.tv_usec = 1;
+            }
+            tv
+        }
+        None => libc::timeval {
+            tv_sec: 0,
+            tv_usec: 0,
+        },
+    };
+
+    let ret = unsafe {
+        libc::setsockopt(stream.as_raw_fd(),
+                         libc::SOL_SOCKET,
+                         opt,
+                         &tv as *const libc::timeval as *const libc::c_void,
+                         mem::size_of::<libc::timeval>() as libc::socklen_t)
+    };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Set the read and write timeouts of a connection.
+///
+/// These are the `SO_RCVTIMEO` and `SO_SNDTIMEO` options of the socket,
+/// `None` disables a timeout. They only bound the blocking system calls
+/// made on the socket, once it is switched back to blocking mode for
+/// instance. The event loop uses the socket in non-blocking mode, so the
+/// futures of this crate and of `tokio_core` aren't affected by them.
+/// A zero duration is an error, like for `std::net::TcpStream`.
+///
+/// This function is only available on Unix platforms.
+pub fn set_socket_timeouts(stream: &TcpStream, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+    try!(set_timeout(stream, libc::SO_RCVTIMEO, read));
+    set_timeout(stream, libc::SO_SNDTIMEO, write)
+}
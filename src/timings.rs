@@ -0,0 +1,104 @@
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::Future;
+use tokio_core::{LoopHandle, TcpStream};
+use tokio_core::io::IoFuture;
+
+use super::common::{tcp_connect_seq_observed, Observer};
+use super::{Resolver, ToEndpoint};
+
+/// The time taken by the phases of a connection.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ConnectTimings {
+    /// The time taken to resolve the host name, zero for a socket address.
+    pub resolve: Duration,
+    /// The time taken by the connection attempts.
+    pub connect: Duration,
+    /// The time from the call to the established connection.
+    pub total: Duration,
+}
+
+// Records when the resolution completes.
+struct TimedResolver<R> {
+    resolver: R,
+    resolved: Arc<Mutex<Option<Instant>>>,
+}
+
+impl<R> Resolver for TimedResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let resolved = self.resolved.clone();
+
+        self.resolver.resolve(host).map(move |addrs| {
+            *resolved.lock().unwrap() = Some(Instant::now());
+            addrs
+        }).boxed()
+    }
+}
+
+/// Connect like `tcp_connect_seq`, and measure the time taken by each phase.
+///
+/// The resolution is over when the resolver returns its addresses, the
+/// connection attempts take the rest of the time, so `resolve` and
+/// `connect` together make up `total`. A socket address endpoint doesn't go through the
+/// resolver, so `resolve` is zero and `connect` is the whole `total`.
+pub fn tcp_connect_timed<'a, R, T>(handle: LoopHandle, resolver: R, ep: T, observer: Option<Observer>)
+                                   -> IoFuture<(TcpStream, ConnectTimings)>
+    where R: Resolver,
+          T: ToEndpoint<'a>
+{
+    let start = Instant::now();
+    let resolved = Arc::new(Mutex::new(None));
+
+    let resolver = TimedResolver {
+        resolver: resolver,
+        resolved: resolved.clone(),
+    };
+
+    tcp_connect_seq_observed(handle, resolver, ep, observer).map(move |stream| {
+        let end = Instant::now();
+        let resolved = resolved.lock().unwrap().unwrap_or(start);
+
+        let timings = ConnectTimings {
+            resolve: resolved.duration_since(start),
+            connect: end.duration_since(resolved),
+            total: end.duration_since(start),
+        };
+
+        debug!("connected in {:?}, {:?} of which resolving", timings.total, timings.resolve);
+        (stream, timings)
+    }).boxed()
+}
+
+#[test]
+fn test_tcp_connect_timed() {
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::thread;
+    use futures::finished;
+    use tokio_core::Loop;
+
+    struct Slow;
+
+    impl Resolver for Slow {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            thread::sleep(Duration::from_millis(20));
+            finished(vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))]).boxed()
+        }
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut lp = Loop::new().unwrap();
+
+    let connect = tcp_connect_timed(lp.handle(), Slow, ("example.com", addr.port()), None);
+    let (_, timings) = lp.run(connect).unwrap();
+    assert!(timings.resolve >= Duration::from_millis(20));
+    assert!(timings.resolve <= timings.total);
+    assert!(timings.resolve + timings.connect <= timings.total);
+
+    let connect = tcp_connect_timed(lp.handle(), Slow, addr, None);
+    let (_, timings) = lp.run(connect).unwrap();
+    assert_eq!(timings.resolve, Duration::from_secs(0));
+    assert_eq!(timings.connect, timings.total);
+}
@@ -0,0 +1,89 @@
+use std::net::SocketAddr;
+
+use tokio_core::{LoopHandle, TcpStream};
+use tokio_core::io::IoFuture;
+
+use super::common::{tcp_connect_par, tcp_connect_seq};
+use super::{Resolver, ToEndpoint};
+
+/// The Transport trait represents an object capable of
+/// establishing connections to socket addresses.
+///
+/// It lets the connect strategies of this crate be used with other
+/// transports than TCP, with `connect_par` and `connect_seq`. There's an
+/// implementation for `LoopHandle` that creates TCP connections.
+pub trait Transport: Clone + Send + 'static {
+    /// The type of the connections established.
+    type Connection: Send + 'static;
+
+    /// Given a socket address, this function returns a Future which
+    /// will eventually resolve into a connection to it.
+    fn connect(&self, addr: &SocketAddr) -> IoFuture<Self::Connection>;
+}
+
+impl Transport for LoopHandle {
+    type Connection = TcpStream;
+
+    fn connect(&self, addr: &SocketAddr) -> IoFuture<TcpStream> {
+        self.clone().tcp_connect(addr)
+    }
+}
+
+/// Create a new connection to the specified endpoint over the given transport.
+///
+/// This behaves like `DnsSupport::tcp_connect_par`: every address the
+/// endpoint resolves to is tried in parallel, and the first connection
+/// established is returned.
+pub fn connect_par<'a, X, R, T>(transport: X, resolver: R, ep: T) -> IoFuture<X::Connection>
+    where X: Transport,
+          R: Resolver,
+          T: ToEndpoint<'a>
+{
+    tcp_connect_par(transport, resolver, ep)
+}
+
+/// Create a new connection to the specified endpoint over the given transport.
+///
+/// This behaves like `DnsSupport::tcp_connect_seq`: the addresses the
+/// endpoint resolves to are tried one after the other, and the first
+/// connection established is returned.
+pub fn connect_seq<'a, X, R, T>(transport: X, resolver: R, ep: T) -> IoFuture<X::Connection>
+    where X: Transport,
+          R: Resolver,
+          T: ToEndpoint<'a>
+{
+    tcp_connect_seq(transport, resolver, ep)
+}
+
+#[test]
+fn test_connect_seq() {
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr};
+    use futures::{failed, finished, Future};
+
+    #[derive(Clone)]
+    struct Fake;
+
+    impl Transport for Fake {
+        type Connection = SocketAddr;
+
+        fn connect(&self, addr: &SocketAddr) -> IoFuture<SocketAddr> {
+            if addr.ip() == IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)) {
+                failed(io::Error::new(io::ErrorKind::ConnectionRefused, "refused")).boxed()
+            } else {
+                finished(*addr).boxed()
+            }
+        }
+    }
+
+    struct Fixed;
+
+    impl Resolver for Fixed {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished(vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))]).boxed()
+        }
+    }
+
+    let addr = connect_seq(Fake, Fixed, ("example.com", 80)).wait().unwrap();
+    assert_eq!(addr, SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 80));
+}
@@ -0,0 +1,60 @@
+use std::net::IpAddr;
+
+use futures::Future;
+use tokio_core::io::IoFuture;
+
+use super::Resolver;
+
+/// A resolver that limits the number of addresses returned.
+///
+/// Only the first `max` addresses returned by the inner resolver are kept.
+/// This protects the connect strategies, in particular the parallel one,
+/// from resolvers returning an unreasonable number of addresses.
+#[derive(Clone)]
+pub struct TruncateResolver<R> {
+    resolver: R,
+    max: usize,
+}
+
+impl<R> TruncateResolver<R> where R: Resolver {
+    /// Create a new TruncateResolver keeping at most `max` addresses.
+    pub fn new(resolver: R, max: usize) -> Self {
+        TruncateResolver {
+            resolver: resolver,
+            max: max,
+        }
+    }
+}
+
+impl<R> Resolver for TruncateResolver<R> where R: Resolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let max = self.max;
+
+        self.resolver.resolve(host).map(move |mut addrs| {
+            if addrs.len() > max {
+                debug!("truncating {} addresses to {}", addrs.len(), max);
+                addrs.truncate(max);
+            }
+            addrs
+        }).boxed()
+    }
+}
+
+#[test]
+fn test_truncate() {
+    use std::net::Ipv4Addr;
+    use futures::finished;
+
+    struct Many;
+
+    impl Resolver for Many {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished((0..10).map(|i| IpAddr::V4(Ipv4Addr::new(10, 0, 0, i))).collect()).boxed()
+        }
+    }
+
+    let addrs = TruncateResolver::new(Many, 3).resolve("example.com").wait().unwrap();
+    assert_eq!(addrs, vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)),
+                           IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                           IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))]);
+}
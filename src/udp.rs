@@ -0,0 +1,56 @@
+use std::io;
+use std::mem;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{Future, Poll};
+use tokio_core::{LoopHandle, UdpSocket};
+use tokio_core::io::IoFuture;
+
+use super::common::{sleep, timed_out};
+
+// The largest payload of a UDP datagram.
+const MAX_DATAGRAM: usize = 65536;
+
+// Future which receives the next datagram on the socket.
+struct RecvFrom {
+    socket: Arc<UdpSocket>,
+    buf: Vec<u8>,
+}
+
+impl Future for RecvFrom {
+    type Item = (Vec<u8>, SocketAddr);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.socket.recv_from(&mut self.buf) {
+            Ok((n, addr)) => {
+                let mut buf = mem::replace(&mut self.buf, Vec::new());
+                buf.truncate(n);
+                Poll::Ok((buf, addr))
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Poll::NotReady,
+            Err(e) => Poll::Err(e),
+        }
+    }
+}
+
+/// Receive the next datagram on a socket, waiting at most `timeout`.
+///
+/// The future resolves into the payload of the datagram and the address it
+/// was sent from. It fails with an error of kind `TimedOut` if no datagram
+/// arrives in time. A datagram that is already waiting is received even
+/// with a zero timeout. The socket is shared so it can be used again once
+/// the future completes, whatever the outcome.
+pub fn udp_recv_timeout(handle: LoopHandle, socket: Arc<UdpSocket>, timeout: Duration)
+                        -> IoFuture<(Vec<u8>, SocketAddr)>
+{
+    let recv = RecvFrom {
+        socket: socket,
+        buf: vec![0; MAX_DATAGRAM],
+    };
+
+    let timeout = sleep(handle, timeout).and_then(|()| Err(timed_out()));
+    recv.select(timeout).map(|(item, _)| item).map_err(|(e, _)| e).boxed()
+}
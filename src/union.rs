@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+use std::io;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use futures::{collect, Future, Poll};
+use futures::stream::Stream;
+use tokio_core::io::{IoFuture, IoStream};
+
+use super::Resolver;
+use super::mapped::unmap;
+
+/// A resolver that merges the results of several resolvers.
+///
+/// Every host name is given to all the resolvers concurrently, and the
+/// addresses they return are merged without duplicates, in the order of
+/// the resolvers. An IPv4 address and its IPv4-mapped IPv6 form count as
+/// duplicates, the first form returned is kept. The resolution fails only
+/// if all the resolvers fail.
+///
+/// With `resolve_stream`, the addresses of each resolver are yielded as
+/// soon as it answers, without waiting for the slower resolvers.
+///
+/// Unlike the `ChainResolver`, which stops at the first resolver that
+/// succeeds, this resolver always combines every answer.
+#[derive(Clone)]
+pub struct UnionResolver {
+    resolvers: Vec<Arc<Resolver + Send + Sync>>,
+}
+
+impl UnionResolver {
+    /// Create a new UnionResolver without any resolvers.
+    pub fn new() -> Self {
+        UnionResolver {
+            resolvers: Vec::new(),
+        }
+    }
+
+    /// Add a resolver whose results are merged.
+    pub fn add<R>(mut self, resolver: R) -> Self
+        where R: Resolver + Send + Sync + 'static
+    {
+        self.resolvers.push(Arc::new(resolver));
+        self
+    }
+}
+
+impl Resolver for UnionResolver {
+    fn resolve(&self, host: &str) -> IoFuture<Vec<IpAddr>> {
+        let futs = self.resolvers.iter().map(|resolver| {
+            resolver.resolve(host).then(|res| Ok::<_, io::Error>(res))
+        }).collect::<Vec<_>>();
+
+        collect(futs).and_then(|results| {
+            let mut addrs = Vec::new();
+            let mut error = None;
+            let mut succeeded = results.is_empty();
+
+            for res in results {
+                match res {
+                    Ok(found) => {
+                        succeeded = true;
+                        for addr in found {
+                            if !addrs.iter().any(|&known| unmap(known) == unmap(addr)) {
+                                addrs.push(addr);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        debug!("a resolver of the union failed: {}", e);
+                        error = error.or(Some(e));
+                    }
+                }
+            }
+
+            match error {
+                Some(e) if !succeeded => Err(e),
+                _ => Ok(addrs),
+            }
+        }).boxed()
+    }
+
+    fn resolve_stream(&self, host: &str) -> IoStream<IpAddr> {
+        UnionStream {
+            futs: self.resolvers.iter().map(|resolver| resolver.resolve(host)).collect(),
+            ready: VecDeque::new(),
+            seen: Vec::new(),
+            error: None,
+            succeeded: self.resolvers.is_empty(),
+        }.boxed()
+    }
+}
+
+// Stream which yields the addresses of each resolver of the union as soon as it answers.
+struct UnionStream {
+    futs: Vec<IoFuture<Vec<IpAddr>>>,
+    ready: VecDeque<IpAddr>,
+    seen: Vec<IpAddr>,
+    error: Option<io::Error>,
+    succeeded: bool,
+}
+
+impl Stream for UnionStream {
+    type Item = IpAddr;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let mut i = 0;
+
+        while i < self.futs.len() {
+            match self.futs[i].poll() {
+                Poll::Ok(found) => {
+                    self.futs.remove(i);
+                    self.succeeded = true;
+                    for addr in found {
+                        if !self.seen.iter().any(|&known| unmap(known) == unmap(addr)) {
+                            self.seen.push(addr);
+                            self.ready.push_back(addr);
+                        }
+                    }
+                }
+                Poll::Err(e) => {
+                    self.futs.remove(i);
+                    debug!("a resolver of the union failed: {}", e);
+                    self.error = self.error.take().or(Some(e));
+                }
+                Poll::NotReady => i += 1,
+            }
+        }
+
+        if let Some(addr) = self.ready.pop_front() {
+            return Poll::Ok(Some(addr));
+        }
+
+        if !self.futs.is_empty() {
+            return Poll::NotReady;
+        }
+
+        match self.error.take() {
+            Some(e) if !self.succeeded => Poll::Err(e),
+            _ => Poll::Ok(None),
+        }
+    }
+}
+
+#[test]
+fn test_union() {
+    use std::net::Ipv4Addr;
+    use futures::{failed, finished};
+
+    struct Fixed(Vec<u8>);
+
+    impl Resolver for Fixed {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            finished(self.0.iter().map(|&i| IpAddr::V4(Ipv4Addr::new(10, 0, 0, i))).collect()).boxed()
+        }
+    }
+
+    struct Failing;
+
+    impl Resolver for Failing {
+        fn resolve(&self, _: &str) -> IoFuture<Vec<IpAddr>> {
+            failed(io::Error::new(io::ErrorKind::Other, "failing")).boxed()
+        }
+    }
+
+    let resolver = UnionResolver::new().add(Fixed(vec![1, 2])).add(Failing).add(Fixed(vec![2, 3]));
+    let addrs = resolver.resolve("example.com").wait().unwrap();
+    assert_eq!(addrs, vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                           IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+                           IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3))]);
+
+    let resolver = UnionResolver::new().add(Fixed(vec![1, 2])).add(Failing).add(Fixed(vec![2, 3]));
+    let addrs = resolver.resolve_stream("example.com").collect().wait().unwrap();
+    assert_eq!(addrs, vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                           IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+                           IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3))]);
+
+    let resolver = UnionResolver::new().add(Failing).add(Failing);
+    assert!(resolver.resolve("example.com").wait().is_err());
+    assert!(resolver.resolve_stream("example.com").collect().wait().is_err());
+}
@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+use std::io;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use futures::{Future, Poll};
+use futures::stream::Stream;
+use tokio_core::LoopHandle;
+use tokio_core::io::{IoFuture, IoStream};
+
+use super::common::sleep;
+use super::Resolver;
+
+enum State {
+    Resolving(IoFuture<Vec<IpAddr>>),
+    Waiting(IoFuture<()>),
+}
+
+// Stream which resolves a host periodically and yields the addresses when they change.
+struct WatchResolution<R> {
+    handle: LoopHandle,
+    resolver: R,
+    host: String,
+    interval: Duration,
+    last: Option<HashSet<IpAddr>>,
+    state: State,
+}
+
+impl<R> Stream for WatchResolution<R> where R: Resolver {
+    type Item = Vec<IpAddr>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            let resolved = match self.state {
+                State::Resolving(ref mut fut) => {
+                    match fut.poll() {
+                        Poll::Ok(addrs) => Some(Ok(addrs)),
+                        Poll::Err(e) => Some(Err(e)),
+                        Poll::NotReady => return Poll::NotReady,
+                    }
+                }
+                State::Waiting(ref mut timeout) => {
+                    match timeout.poll() {
+                        Poll::Ok(()) => None,
+                        Poll::Err(e) => return Poll::Err(e),
+                        Poll::NotReady => return Poll::NotReady,
+                    }
+                }
+            };
+
+            match resolved {
+                None => {
+                    self.state = State::Resolving(self.resolver.resolve(&self.host));
+                }
+                Some(res) => {
+                    self.state = State::Waiting(sleep(self.handle.clone(), self.interval));
+
+                    match res {
+                        Ok(addrs) => {
+                            let set = addrs.iter().cloned().collect();
+                            if self.last.as_ref() != Some(&set) {
+                                debug!("resolution of {} changed", self.host);
+                                self.last = Some(set);
+                                return Poll::Ok(Some(addrs));
+                            }
+                        }
+                        Err(e) => {
+                            warn!("could not resolve {}: {}", self.host, e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn watch_resolution<R>(handle: LoopHandle, resolver: R, host: &str, interval: Duration) -> IoStream<Vec<IpAddr>>
+    where R: Resolver + Send + 'static
+{
+    WatchResolution {
+        state: State::Resolving(resolver.resolve(host)),
+        handle: handle,
+        resolver: resolver,
+        host: host.to_owned(),
+        interval: interval,
+        last: None,
+    }.boxed()
+}